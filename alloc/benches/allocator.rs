@@ -0,0 +1,112 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Benchmarks of the [`mc_sgx_alloc::Allocator`] from inside of the test
+//! enclave.
+//!
+//! Allocations with natural alignment use the SGX SDK's `realloc()` and
+//! `calloc()`, while over aligned allocations fall back to copying and
+//! zeroing by hand. Comparing the two shows the benefit of the native
+//! implementations.
+//!
+//! The churn benchmarks allocate and free small blocks from several threads
+//! at once, comparing the contention on the SGX SDK's heap with and without a
+//! [`mc_sgx_alloc::ThreadCache`] as the global allocator.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use mc_sgx_urts::EnclaveBuilder;
+use mc_sgx_util::ResultInto;
+use std::thread;
+use test_enclave::{
+    ecall_alloc_churn, ecall_alloc_realloc, ecall_alloc_zeroed, ENCLAVE, ENCLAVE_THREAD_CACHE,
+};
+
+/// Alignments which use the `malloc()` family and `sgx_aligned_malloc()`
+/// respectively
+const ALIGNMENTS: [(&str, usize); 2] = [("natural", 8), ("over_aligned", 64)];
+
+/// The sizes to benchmark, limited by the test enclave's 1 MiB heap
+const SIZES: [usize; 3] = [1 << 10, 1 << 14, 1 << 18];
+
+/// The enclaves to churn, by their global allocator
+const CHURNERS: [(&str, &[u8]); 2] = [
+    ("allocator", ENCLAVE),
+    ("thread_cache", ENCLAVE_THREAD_CACHE),
+];
+
+/// The threads to churn with, limited by the test enclave's 8 TCSs
+const THREADS: [usize; 3] = [1, 4, 8];
+
+/// The size of the blocks to churn
+const CHURN_SIZE: usize = 64;
+
+/// The rounds of allocating and freeing per ecall
+const CHURN_ROUNDS: usize = 1000;
+
+fn realloc(c: &mut Criterion) {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+    let id = *enclave.id();
+
+    let mut group = c.benchmark_group("realloc");
+    for (name, align) in ALIGNMENTS {
+        for size in SIZES {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
+                b.iter(|| {
+                    let mut preserved = 0;
+                    unsafe { ecall_alloc_realloc(id, align, 16, size, &mut preserved) }
+                        .into_result()
+                        .unwrap();
+                    preserved
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn alloc_zeroed(c: &mut Criterion) {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+    let id = *enclave.id();
+
+    let mut group = c.benchmark_group("alloc_zeroed");
+    for (name, align) in ALIGNMENTS {
+        for size in SIZES {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
+                b.iter(|| {
+                    let mut zeroed = 0;
+                    unsafe { ecall_alloc_zeroed(id, align, size, &mut zeroed) }
+                        .into_result()
+                        .unwrap();
+                    zeroed
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("churn");
+    for (name, enclave) in CHURNERS {
+        let enclave = EnclaveBuilder::from(enclave).create().unwrap();
+        let id = *enclave.id();
+        for threads in THREADS {
+            group.bench_with_input(BenchmarkId::new(name, threads), &threads, |b, &threads| {
+                b.iter(|| {
+                    thread::scope(|s| {
+                        for _ in 0..threads {
+                            s.spawn(|| {
+                                unsafe { ecall_alloc_churn(id, CHURN_SIZE, CHURN_ROUNDS) }
+                                    .into_result()
+                                    .unwrap()
+                            });
+                        }
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, realloc, alloc_zeroed, churn);
+criterion_main!(benches);
@@ -1,22 +1,175 @@
 // Copyright (c) 2022 The MobileCoin Foundation
 
+use crate::backend::{self, Backend};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ffi::c_void;
-use core::ptr::null_mut;
+use core::ptr::{self, null_mut};
 use mc_sgx_tservice_sys::{sgx_aligned_free, sgx_aligned_malloc};
 
+/// The alignment guaranteed by the SGX SDK's `malloc()`.
+///
+/// The SGX SDK uses dlmalloc, which aligns to twice the pointer size.
+const MIN_ALIGN: usize = 2 * core::mem::size_of::<usize>();
+
 /// Allocator that works in an SGX enclave
+///
+/// Allocations with an alignment of at most `MIN_ALIGN` use the SGX SDK's
+/// `malloc()` family. This allows for [`GlobalAlloc::realloc()`] to grow in
+/// place and [`GlobalAlloc::alloc_zeroed()`] to use `calloc()`. Allocations
+/// with a larger alignment use `sgx_aligned_malloc()`, which has no in place
+/// growth, so they are reallocated by copying into a new allocation.
 #[derive(Debug)]
 pub struct Allocator;
 
 unsafe impl GlobalAlloc for Allocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        backend::alloc(&SdkHeap, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        backend::dealloc(&SdkHeap, ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        backend::alloc_zeroed(&SdkHeap, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        backend::realloc(&SdkHeap, ptr, layout, new_size)
+    }
+}
+
+/// The SGX SDK's heap as a [`Backend`], the memory of the [`Allocator`] and
+/// the fallback of the [`Bump`](crate::Bump) and [`Slab`](crate::Slab)
+/// backends
+///
+/// This is a separate type from the [`Allocator`], so that calling the
+/// methods shared by [`GlobalAlloc`] and [`Backend`] isn't ambiguous. It isn't
+/// exported, it's only the default fallback of the backends.
+#[derive(Debug)]
+pub struct SdkHeap;
+
+unsafe impl Backend for SdkHeap {
+    const INIT: Self = SdkHeap;
+
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
         let size = layout.size();
-        let align = layout.align();
-        let memory = sgx_aligned_malloc(size, align, null_mut(), 0);
+        let memory = if is_natural(layout) {
+            malloc(size)
+        } else {
+            sgx_aligned_malloc(size, layout.align(), null_mut(), 0)
+        };
         memory as *mut u8
     }
-    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
-        sgx_aligned_free(ptr as *mut c_void);
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if is_natural(layout) {
+            free(ptr as *mut c_void);
+        } else {
+            sgx_aligned_free(ptr as *mut c_void);
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if !is_natural(layout) {
+            let memory = self.alloc(layout);
+            if !memory.is_null() {
+                ptr::write_bytes(memory, 0, layout.size());
+            }
+            return memory;
+        }
+
+        calloc(layout.size(), 1) as *mut u8
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // The alignment is unchanged by a reallocation, so the new memory comes
+        // from the same family as `ptr` did.
+        if !is_natural(layout) {
+            return backend::realloc_by_copy(self, ptr, layout, new_size);
+        }
+
+        realloc(ptr as *mut c_void, new_size) as *mut u8
+    }
+}
+
+/// Is `layout` satisfied by the alignment of the `malloc()` family
+fn is_natural(layout: Layout) -> bool {
+    layout.align() <= MIN_ALIGN
+}
+
+extern "C" {
+    /// The SGX SDK's `malloc()`, from `sgx_tstdc`
+    fn malloc(size: usize) -> *mut c_void;
+
+    /// The SGX SDK's `calloc()`, from `sgx_tstdc`
+    fn calloc(count: usize, size: usize) -> *mut c_void;
+
+    /// The SGX SDK's `realloc()`, from `sgx_tstdc`
+    fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+
+    /// The SGX SDK's `free()`, from `sgx_tstdc`
+    fn free(ptr: *mut c_void);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mc_sgx_urts::EnclaveBuilder;
+    use mc_sgx_util::ResultInto;
+    use test_enclave::{ecall_alloc_realloc, ecall_alloc_zeroed, ENCLAVE};
+    use yare::parameterized;
+
+    #[parameterized(
+    byte = {1, true},
+    min_align = {MIN_ALIGN, true},
+    over_aligned = {MIN_ALIGN * 2, false},
+    page = {4096, false},
+    )]
+    fn natural_alignment(align: usize, expected: bool) {
+        let layout = Layout::from_size_align(64, align).expect("Layout should be valid");
+
+        assert_eq!(is_natural(layout), expected);
+    }
+
+    #[parameterized(
+    natural_small = {8, 1, 100},
+    natural_large = {8, 16, 1 << 18},
+    min_align = {MIN_ALIGN, 3, 5000},
+    over_aligned_small = {64, 1, 100},
+    over_aligned_large = {4096, 16, 1 << 18},
+    )]
+    fn realloc_preserves_contents(align: usize, start_size: usize, end_size: usize) {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        let mut preserved = 0;
+        unsafe { ecall_alloc_realloc(id, align, start_size, end_size, &mut preserved) }
+            .into_result()
+            .unwrap();
+
+        assert_ne!(preserved, 0);
+    }
+
+    #[parameterized(
+    natural_small = {8, 10},
+    natural_large = {8, 1 << 18},
+    over_aligned_small = {64, 10},
+    over_aligned_large = {4096, 1 << 18},
+    )]
+    fn alloc_zeroed_is_zero(align: usize, size: usize) {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        // Twice so that the second allocation may reuse the dirtied memory of
+        // the first
+        for _ in 0..2 {
+            let mut zeroed = 0;
+            unsafe { ecall_alloc_zeroed(id, align, size, &mut zeroed) }
+                .into_result()
+                .unwrap();
+
+            assert_ne!(zeroed, 0);
+        }
     }
 }
@@ -0,0 +1,169 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! A fixed region of memory which is handed out sequentially
+
+use core::alloc::Layout;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The memory of an [`Arena`]
+///
+/// Page aligned, so that blocks aligned to their size, up to a page, fit
+/// without padding at the start.
+#[repr(C, align(4096))]
+struct Region<const SIZE: usize>([u8; SIZE]);
+
+/// A region of `SIZE` bytes handed out front to back
+///
+/// The region is zero initialized, so an arena in a `static` is placed in the
+/// enclave's `.bss` and is reserved when the enclave is loaded, separate from
+/// the SGX SDK's heap.
+#[derive(Debug)]
+pub(crate) struct Arena<const SIZE: usize> {
+    region: UnsafeCell<Region<SIZE>>,
+    /// The offset of the first unused byte of `region`
+    next: AtomicUsize,
+}
+
+// SAFETY: The region is only handed out through `next`, which is atomically
+// advanced, so no two callers get overlapping memory.
+unsafe impl<const SIZE: usize> Sync for Arena<SIZE> {}
+
+impl<const SIZE: usize> Arena<SIZE> {
+    /// Create a new [`Arena`] with all of the region unused
+    pub(crate) const fn new() -> Self {
+        Self {
+            region: UnsafeCell::new(Region([0; SIZE])),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Take the memory for `layout` from the unused part of the region
+    ///
+    /// Returns null when the unused part of the region is too small.
+    pub(crate) fn alloc(&self, layout: Layout) -> *mut u8 {
+        let base = self.base() as usize;
+        let mut current = self.next.load(Ordering::Relaxed);
+        loop {
+            let Some((start, end)) = Self::fit(base, current, layout) else {
+                return core::ptr::null_mut();
+            };
+            match self.next.compare_exchange_weak(
+                current,
+                end,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                // SAFETY: `start` is within the region
+                Ok(_) => return unsafe { self.base().add(start) },
+                Err(next) => current = next,
+            }
+        }
+    }
+
+    /// Return the memory at `ptr` to the unused part of the region
+    ///
+    /// This is only possible for the most recent allocation, for any other
+    /// allocation the memory remains in use until the arena is dropped.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by this arena with `layout`.
+    pub(crate) unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let start = ptr as usize - self.base() as usize;
+        // A failure means this wasn't the most recent allocation
+        let _ = self.next.compare_exchange(
+            start + layout.size(),
+            start,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Does `ptr` point into the region
+    pub(crate) fn contains(&self, ptr: *mut u8) -> bool {
+        let base = self.base() as usize;
+        (base..base + SIZE).contains(&(ptr as usize))
+    }
+
+    /// The start of the region
+    fn base(&self) -> *mut u8 {
+        self.region.get() as *mut u8
+    }
+
+    /// The offsets, `(start, end)`, where `layout` fits after `current` in
+    /// the region at `base`
+    fn fit(base: usize, current: usize, layout: Layout) -> Option<(usize, usize)> {
+        let unaligned = base.checked_add(current)?;
+        let aligned = unaligned.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let start = aligned - base;
+        let end = start.checked_add(layout.size())?;
+        (end <= SIZE).then_some((start, end))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use yare::parameterized;
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).expect("Layout should be valid")
+    }
+
+    #[parameterized(
+    byte = {1, 1},
+    word = {8, 8},
+    over_aligned = {10, 64},
+    whole = {256, 1},
+    )]
+    fn allocation_is_aligned_and_contained(size: usize, align: usize) {
+        let arena = Arena::<256>::new();
+
+        let first = arena.alloc(layout(1, 1));
+        let ptr = arena.alloc(layout(size, align));
+
+        if size == 256 {
+            assert!(ptr.is_null());
+        } else {
+            assert!(!ptr.is_null());
+            assert_ne!(first, ptr);
+            assert_eq!(ptr as usize % align, 0);
+            assert!(arena.contains(ptr));
+            assert!(arena.contains(unsafe { ptr.add(size - 1) }));
+        }
+    }
+
+    #[test]
+    fn exhausted_arena_returns_null() {
+        let arena = Arena::<64>::new();
+
+        assert!(!arena.alloc(layout(32, 1)).is_null());
+        assert!(!arena.alloc(layout(32, 1)).is_null());
+        assert!(arena.alloc(layout(1, 1)).is_null());
+    }
+
+    #[test]
+    fn most_recent_allocation_is_reused() {
+        let arena = Arena::<64>::new();
+        let first = arena.alloc(layout(16, 8));
+        let second = arena.alloc(layout(16, 8));
+
+        // Not the most recent, so remains in use
+        unsafe { arena.dealloc(first, layout(16, 8)) };
+        let third = arena.alloc(layout(16, 8));
+        assert_ne!(third, first);
+
+        unsafe { arena.dealloc(third, layout(16, 8)) };
+        assert_eq!(arena.alloc(layout(16, 8)), third);
+        assert_ne!(second, third);
+    }
+
+    #[test]
+    fn outside_pointer_is_not_contained() {
+        let arena = Arena::<64>::new();
+        let mut outside = 0u8;
+
+        assert!(!arena.contains(&mut outside));
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Pluggable sources of memory for the global allocator
+//!
+//! The [`Allocator`](crate::Allocator) gets all of its memory from the SGX
+//! SDK, which serializes every allocation on one lock. A [`Backend`] allows
+//! the memory to come from elsewhere, like the [`Bump`](crate::Bump) or
+//! [`Slab`](crate::Slab) allocators which carve their memory from a region
+//! reserved when the enclave is loaded.
+
+use crate::{limit, stats};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// A source of memory for a [`BackendAllocator`]
+///
+/// The methods mirror those of [`GlobalAlloc`], without the heap accounting,
+/// which is done by the [`BackendAllocator`].
+///
+/// # Safety
+///
+/// Implementations must uphold the same contract as [`GlobalAlloc`].
+pub unsafe trait Backend: Sync {
+    /// The initial state of the backend.
+    ///
+    /// Used to construct the backend in the `static` declared by
+    /// [`allocator!`](crate::allocator!).
+    const INIT: Self;
+
+    /// Allocate memory as described by `layout`
+    ///
+    /// # Safety
+    ///
+    /// See [`GlobalAlloc::alloc()`]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8;
+
+    /// Deallocate the block of memory at `ptr` with `layout`
+    ///
+    /// # Safety
+    ///
+    /// See [`GlobalAlloc::dealloc()`]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+
+    /// Allocate zero initialized memory as described by `layout`
+    ///
+    /// # Safety
+    ///
+    /// See [`GlobalAlloc::alloc_zeroed()`]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let memory = self.alloc(layout);
+        if !memory.is_null() {
+            ptr::write_bytes(memory, 0, layout.size());
+        }
+        memory
+    }
+
+    /// Resize the block of memory at `ptr` to `new_size` bytes
+    ///
+    /// # Safety
+    ///
+    /// See [`GlobalAlloc::realloc()`]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        realloc_by_copy(self, ptr, layout, new_size)
+    }
+}
+
+/// Reallocate by copying into a new allocation from `backend`
+///
+/// # Safety
+///
+/// See [`GlobalAlloc::realloc()`]
+pub(crate) unsafe fn realloc_by_copy<B: Backend + ?Sized>(
+    backend: &B,
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+) -> *mut u8 {
+    let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+    let new_ptr = backend.alloc(new_layout);
+    if !new_ptr.is_null() {
+        ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+        backend.dealloc(ptr, layout);
+    }
+    new_ptr
+}
+
+/// A global allocator which gets its memory from a [`Backend`]
+///
+/// The heap usage is tracked the same as for the
+/// [`Allocator`](crate::Allocator). Usually declared with
+/// [`allocator!`](crate::allocator!).
+#[derive(Debug)]
+pub struct BackendAllocator<B> {
+    backend: B,
+}
+
+impl<B> BackendAllocator<B> {
+    /// Create a new [`BackendAllocator`] getting its memory from `backend`
+    pub const fn new(backend: B) -> Self {
+        Self { backend }
+    }
+}
+
+unsafe impl<B: Backend> GlobalAlloc for BackendAllocator<B> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc(&self.backend, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        dealloc(&self.backend, ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        alloc_zeroed(&self.backend, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        realloc(&self.backend, ptr, layout, new_size)
+    }
+}
+
+/// [`GlobalAlloc::alloc()`] from `backend`, recording the heap usage and
+/// enforcing the current thread's limit
+pub(crate) unsafe fn alloc<B: Backend>(backend: &B, layout: Layout) -> *mut u8 {
+    if !limit::try_charge(layout.size()) {
+        return ptr::null_mut();
+    }
+    let memory = backend.alloc(layout);
+    record_alloc(memory, layout.size());
+    memory
+}
+
+/// [`GlobalAlloc::dealloc()`] to `backend`, recording the heap usage
+pub(crate) unsafe fn dealloc<B: Backend>(backend: &B, ptr: *mut u8, layout: Layout) {
+    #[cfg(feature = "zeroize")]
+    zeroize(ptr, layout.size());
+    backend.dealloc(ptr, layout);
+    limit::refund(layout.size());
+    stats::record_dealloc(layout.size());
+}
+
+/// [`GlobalAlloc::alloc_zeroed()`] from `backend`, recording the heap usage
+/// and enforcing the current thread's limit
+pub(crate) unsafe fn alloc_zeroed<B: Backend>(backend: &B, layout: Layout) -> *mut u8 {
+    if !limit::try_charge(layout.size()) {
+        return ptr::null_mut();
+    }
+    let memory = backend.alloc_zeroed(layout);
+    record_alloc(memory, layout.size());
+    memory
+}
+
+/// [`GlobalAlloc::realloc()`] with `backend`, recording the heap usage and
+/// enforcing the current thread's limit
+pub(crate) unsafe fn realloc<B: Backend>(
+    backend: &B,
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+) -> *mut u8 {
+    let old_size = layout.size();
+    let growth = new_size.saturating_sub(old_size);
+    if !limit::try_charge(growth) {
+        return ptr::null_mut();
+    }
+    #[cfg(feature = "zeroize")]
+    let memory = realloc_zeroizing(backend, ptr, layout, new_size);
+    #[cfg(not(feature = "zeroize"))]
+    let memory = backend.realloc(ptr, layout, new_size);
+    if memory.is_null() {
+        limit::refund(growth);
+    } else {
+        limit::refund(old_size.saturating_sub(new_size));
+        stats::record_dealloc(old_size);
+        stats::record_alloc(new_size);
+    }
+    memory
+}
+
+/// Zero the `size` bytes at `ptr`, prior to them being freed
+///
+/// The writes are volatile so that they aren't optimized away as dead stores.
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes of `size` bytes.
+#[cfg(feature = "zeroize")]
+unsafe fn zeroize(ptr: *mut u8, size: usize) {
+    core::slice::from_raw_parts_mut(ptr, size).zeroize();
+}
+
+/// Reallocate by copying into a new allocation, zeroing the old allocation
+/// prior to freeing it
+///
+/// A backend's own reallocation may move the memory and free the old
+/// allocation without zeroing it, so it isn't used.
+///
+/// # Safety
+///
+/// See [`GlobalAlloc::realloc()`]
+#[cfg(feature = "zeroize")]
+unsafe fn realloc_zeroizing<B: Backend>(
+    backend: &B,
+    ptr: *mut u8,
+    layout: Layout,
+    new_size: usize,
+) -> *mut u8 {
+    let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+    let new_ptr = backend.alloc(new_layout);
+    if !new_ptr.is_null() {
+        ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+        zeroize(ptr, layout.size());
+        backend.dealloc(ptr, layout);
+    }
+    new_ptr
+}
+
+/// Record the result, `memory`, of allocating `size` bytes
+///
+/// A failed allocation is refunded to the current thread's limit.
+fn record_alloc(memory: *mut u8, size: usize) {
+    if memory.is_null() {
+        limit::refund(size);
+    } else {
+        stats::record_alloc(size);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    extern crate std;
+
+    use super::*;
+    use std::alloc::System;
+    #[cfg(feature = "zeroize")]
+    use yare::parameterized;
+
+    /// A backend which is always out of memory, for testing the fallback of
+    /// other backends
+    #[derive(Debug)]
+    pub(crate) struct Null;
+
+    unsafe impl Backend for Null {
+        const INIT: Self = Null;
+
+        unsafe fn alloc(&self, _layout: Layout) -> *mut u8 {
+            ptr::null_mut()
+        }
+
+        unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
+            panic!("Nothing was allocated from the `Null` backend");
+        }
+    }
+
+    /// A backend using the host's allocator
+    #[derive(Debug)]
+    pub(crate) struct Host;
+
+    unsafe impl Backend for Host {
+        const INIT: Self = Host;
+
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    /// A backend using the host's allocator, which checks that memory is
+    /// zeroed before it's freed
+    #[cfg(feature = "zeroize")]
+    #[derive(Debug)]
+    struct Zeroed;
+
+    #[cfg(feature = "zeroize")]
+    unsafe impl Backend for Zeroed {
+        const INIT: Self = Zeroed;
+
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            assert!(std::slice::from_raw_parts(ptr, layout.size())
+                .iter()
+                .all(|b| *b == 0));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    // The backend is used directly, rather than through a `BackendAllocator`,
+    // so that the global heap usage isn't disturbed for the `stats` tests.
+    #[test]
+    fn default_realloc_preserves_contents() {
+        let backend = Host;
+        let layout = Layout::from_size_align(16, 64).expect("Layout should be valid");
+
+        unsafe {
+            let ptr = backend.alloc(layout);
+            assert!(!ptr.is_null());
+            ptr::copy_nonoverlapping(b"0123456789abcdef".as_ptr(), ptr, 16);
+
+            let ptr = backend.realloc(ptr, layout, 1000);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % 64, 0);
+            assert_eq!(std::slice::from_raw_parts(ptr, 16), b"0123456789abcdef");
+
+            backend.dealloc(ptr, Layout::from_size_align_unchecked(1000, 64));
+        }
+    }
+
+    #[test]
+    fn default_alloc_zeroed_is_zero() {
+        let backend = Host;
+        let layout = Layout::from_size_align(100, 8).expect("Layout should be valid");
+
+        unsafe {
+            let ptr = backend.alloc(layout);
+            ptr::write_bytes(ptr, 0xAB, 100);
+            backend.dealloc(ptr, layout);
+
+            let ptr = backend.alloc_zeroed(layout);
+            assert!(!ptr.is_null());
+            assert!(std::slice::from_raw_parts(ptr, 100).iter().all(|b| *b == 0));
+            backend.dealloc(ptr, layout);
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroize_clears_memory() {
+        let mut secret = *b"secret";
+
+        unsafe { zeroize(secret.as_mut_ptr(), secret.len()) };
+
+        assert_eq!(secret, [0; 6]);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[parameterized(
+    grow = {16, 1000},
+    shrink = {1000, 16},
+    )]
+    fn realloc_zeroizes_source(size: usize, new_size: usize) {
+        let layout = Layout::from_size_align(size, 8).expect("Layout should be valid");
+
+        unsafe {
+            let ptr = Zeroed.alloc(layout);
+            ptr::write_bytes(ptr, 0xAB, size);
+
+            let ptr = realloc_zeroizing(&Zeroed, ptr, layout, new_size);
+            assert!(!ptr.is_null());
+            assert!(std::slice::from_raw_parts(ptr, size.min(new_size))
+                .iter()
+                .all(|b| *b == 0xAB));
+
+            System.dealloc(ptr, Layout::from_size_align_unchecked(new_size, 8));
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! A bump allocator backend
+
+use crate::allocator::SdkHeap;
+use crate::arena::Arena;
+use crate::Backend;
+use core::alloc::Layout;
+
+/// A [`Backend`] which hands out a fixed region of `SIZE` bytes front to back
+///
+/// An allocation only takes an atomic increment, there is no lock, so threads
+/// don't contend with each other. The memory of an allocation is only reused
+/// when it's the most recently made allocation, so this suits enclaves which
+/// make their long lived allocations up front.
+///
+/// Once the region is used up, allocations come from the `F` backend, the SGX
+/// SDK's heap by default.
+///
+/// # Example
+///
+/// ```ignore
+/// // 4 MiB bump allocator, the region counts against the enclave's size
+/// mc_sgx_alloc::allocator!(ALLOCATOR, mc_sgx_alloc::Bump<{ 4 << 20 }>);
+/// ```
+#[derive(Debug)]
+pub struct Bump<const SIZE: usize, F = SdkHeap> {
+    arena: Arena<SIZE>,
+    fallback: F,
+}
+
+unsafe impl<const SIZE: usize, F: Backend> Backend for Bump<SIZE, F> {
+    const INIT: Self = Self {
+        arena: Arena::new(),
+        fallback: F::INIT,
+    };
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let memory = self.arena.alloc(layout);
+        if memory.is_null() {
+            self.fallback.alloc(layout)
+        } else {
+            memory
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if self.arena.contains(ptr) {
+            self.arena.dealloc(ptr, layout);
+        } else {
+            self.fallback.dealloc(ptr, layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test::Null;
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).expect("Layout should be valid")
+    }
+
+    #[test]
+    fn allocations_come_from_the_region() {
+        let bump = Bump::<128, Null>::INIT;
+
+        unsafe {
+            let first = bump.alloc(layout(32, 8));
+            let second = bump.alloc(layout(32, 8));
+
+            assert!(bump.arena.contains(first));
+            assert!(bump.arena.contains(second));
+            assert_ne!(first, second);
+
+            bump.dealloc(first, layout(32, 8));
+            bump.dealloc(second, layout(32, 8));
+        }
+    }
+
+    #[test]
+    fn exhausted_region_uses_fallback() {
+        let bump = Bump::<64, Null>::INIT;
+
+        unsafe {
+            assert!(!bump.alloc(layout(64, 1)).is_null());
+            // `Null` is always out of memory
+            assert!(bump.alloc(layout(1, 1)).is_null());
+        }
+    }
+
+    #[test]
+    fn realloc_preserves_contents() {
+        let bump = Bump::<128, Null>::INIT;
+
+        unsafe {
+            let ptr = bump.alloc(layout(4, 4));
+            core::ptr::copy_nonoverlapping(b"abcd".as_ptr(), ptr, 4);
+
+            let ptr = bump.realloc(ptr, layout(4, 4), 32);
+
+            assert!(!ptr.is_null());
+            assert_eq!(core::slice::from_raw_parts(ptr, 4), b"abcd");
+        }
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! A per thread cache of small blocks
+//!
+//! Each thread keeps its freed small blocks in free lists, one per power of
+//! two size class, in `#[thread_local]` storage. The free lists are only
+//! touched by their own thread so they need no lock, the backend, and its
+//! lock, is only involved when a thread's free list is empty or overfull.
+
+use crate::allocator::SdkHeap;
+use crate::backend::realloc_by_copy;
+use crate::Backend;
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::ptr;
+
+/// The smallest size class, large enough to hold a [`Block`]
+const MIN_CLASS: usize = 16;
+
+/// The largest size class, larger allocations aren't cached
+const MAX_CLASS: usize = 256;
+
+/// The number of size classes, the powers of two from [`MIN_CLASS`] to
+/// [`MAX_CLASS`]
+const CLASSES: usize = (MAX_CLASS.trailing_zeros() - MIN_CLASS.trailing_zeros() + 1) as usize;
+
+/// The alignment of the cached blocks.
+///
+/// Allocations needing a larger alignment aren't cached.
+const BLOCK_ALIGN: usize = 16;
+
+/// The most blocks a thread caches per size class.
+///
+/// Bounds the memory held by each thread to just under 16 KiB.
+const MAX_CACHED: usize = 32;
+
+/// The number of blocks returned to the backend at once when a free list
+/// overflows [`MAX_CACHED`]
+const BATCH: usize = MAX_CACHED / 2;
+
+/// The free lists of the current thread
+#[thread_local]
+static CACHE: [FreeList; CLASSES] = [FreeList::EMPTY; CLASSES];
+
+/// A free block of a size class
+struct Block {
+    next: *mut Block,
+}
+
+/// The cached blocks of one size class, for one thread
+struct FreeList {
+    /// The most recently freed block
+    head: Cell<*mut Block>,
+    /// The number of blocks in the list
+    len: Cell<usize>,
+}
+
+impl FreeList {
+    /// An empty list, for initializing the array of free lists
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = Self {
+        head: Cell::new(ptr::null_mut()),
+        len: Cell::new(0),
+    };
+
+    /// Take a cached block, if any
+    fn pop(&self) -> *mut u8 {
+        let head = self.head.get();
+        if !head.is_null() {
+            // SAFETY: Blocks in the list are unused and only accessed by this
+            // thread
+            self.head.set(unsafe { (*head).next });
+            self.len.set(self.len.get() - 1);
+        }
+        head as *mut u8
+    }
+
+    /// Add `block` to the list, returning the new length
+    ///
+    /// # Safety
+    ///
+    /// `block` must be an unused block of this list's size class.
+    unsafe fn push(&self, block: *mut u8) -> usize {
+        let block = block as *mut Block;
+        (*block).next = self.head.get();
+        self.head.set(block);
+        let len = self.len.get() + 1;
+        self.len.set(len);
+        len
+    }
+}
+
+/// A [`Backend`] which caches small blocks from the SGX SDK's heap per thread
+///
+/// Allocations of at most 256 bytes, with an alignment of at most 16 bytes,
+/// are rounded up to a power of two size class. Freed blocks are kept by the
+/// freeing thread, up to 32 blocks per size class, and handed back out by
+/// that thread without touching the heap. When a thread's free list is full,
+/// half of it is returned to the heap as a batch.
+///
+/// The cache is shared by all instances, they all get their blocks from the
+/// SGX SDK's heap so blocks can be freed to any of them.
+///
+/// The cached blocks live in the thread local storage of the TCS. With a
+/// `TCSPolicy` of unbind, `1`, the SGX SDK reinitializes the thread local
+/// storage for each ecall, which leaks the blocks cached during the previous
+/// ecall. Either use a `TCSPolicy` of bind, `0`, or run the body of each ecall
+/// in [`with_thread_cache()`].
+///
+/// # Example
+///
+/// ```ignore
+/// mc_sgx_alloc::allocator!(ALLOCATOR, mc_sgx_alloc::ThreadCache);
+///
+/// #[no_mangle]
+/// pub extern "C" fn ecall_work() {
+///     mc_sgx_alloc::with_thread_cache(|| {
+///         // Allocate as usual
+///     })
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ThreadCache {
+    cache: Cache<SdkHeap>,
+}
+
+unsafe impl Backend for ThreadCache {
+    const INIT: Self = Self { cache: Cache::INIT };
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.cache.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.cache.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.cache.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.cache.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Return all of the blocks cached by the current thread, for the
+/// [`ThreadCache`], to the SGX SDK's heap
+///
+/// Prefer [`with_thread_cache()`], which can't miss a path out of an ecall.
+pub fn flush_thread_cache() {
+    Cache::<SdkHeap>::INIT.flush();
+}
+
+/// Run `f`, then return all of the blocks cached by the current thread, for
+/// the [`ThreadCache`], to the SGX SDK's heap
+///
+/// The blocks are returned however `f` exits, including by an early return or
+/// by unwinding. Wrap the body of each ecall in it when using a
+/// [`ThreadCache`] with a `TCSPolicy` of unbind, `1`, so that the blocks
+/// cached during the ecall aren't leaked.
+pub fn with_thread_cache<R>(f: impl FnOnce() -> R) -> R {
+    Cache::<SdkHeap>::INIT.scope(f)
+}
+
+/// The caching of a [`ThreadCache`], in front of the `B` backend
+///
+/// All instances share the free lists of the current thread, regardless of
+/// `B`. Only the [`ThreadCache`], with the SGX SDK's heap, is used in the
+/// enclave, other backends are for testing.
+#[derive(Debug)]
+struct Cache<B> {
+    inner: B,
+}
+
+impl<B: Backend> Cache<B> {
+    /// Return all of the blocks cached by the current thread to the backend
+    fn flush(&self) {
+        for (class, free_list) in CACHE.iter().enumerate() {
+            loop {
+                let block = free_list.pop();
+                if block.is_null() {
+                    break;
+                }
+                // SAFETY: The cached blocks were allocated from `inner` with
+                // the block layout of their class
+                unsafe { self.inner.dealloc(block, block_layout(class)) };
+            }
+        }
+    }
+
+    /// Run `f`, then [`flush()`](Self::flush) the cache however `f` exits
+    fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _scope = Scope { cache: self };
+        f()
+    }
+}
+
+/// Flushes the cache when a scope ends, including by unwinding
+struct Scope<'a, B: Backend> {
+    cache: &'a Cache<B>,
+}
+
+impl<B: Backend> Drop for Scope<'_, B> {
+    fn drop(&mut self) {
+        self.cache.flush();
+    }
+}
+
+unsafe impl<B: Backend> Backend for Cache<B> {
+    const INIT: Self = Self { inner: B::INIT };
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(class) = size_class(layout) else {
+            return self.inner.alloc(layout);
+        };
+
+        let block = CACHE[class].pop();
+        if block.is_null() {
+            self.inner.alloc(block_layout(class))
+        } else {
+            block
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some(class) = size_class(layout) else {
+            return self.inner.dealloc(ptr, layout);
+        };
+
+        let free_list = &CACHE[class];
+        if free_list.push(ptr) > MAX_CACHED {
+            for _ in 0..BATCH {
+                self.inner.dealloc(free_list.pop(), block_layout(class));
+            }
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if size_class(layout).is_none() {
+            return self.inner.alloc_zeroed(layout);
+        }
+
+        let memory = self.alloc(layout);
+        if !memory.is_null() {
+            ptr::write_bytes(memory, 0, layout.size());
+        }
+        memory
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        match (size_class(layout), size_class(new_layout)) {
+            (None, None) => self.inner.realloc(ptr, layout, new_size),
+            (old, new) if old == new => ptr,
+            _ => realloc_by_copy(self, ptr, layout, new_size),
+        }
+    }
+}
+
+/// The index of the size class for `layout`, `None` when it isn't cached
+fn size_class(layout: Layout) -> Option<usize> {
+    if layout.align() > BLOCK_ALIGN || layout.size() > MAX_CLASS {
+        return None;
+    }
+    let size = layout.size().max(MIN_CLASS).next_power_of_two();
+    Some((size.trailing_zeros() - MIN_CLASS.trailing_zeros()) as usize)
+}
+
+/// The layout of the blocks in size class `class`
+fn block_layout(class: usize) -> Layout {
+    // SAFETY: The size classes are powers of two no smaller than the
+    // alignment
+    unsafe { Layout::from_size_align_unchecked(MIN_CLASS << class, BLOCK_ALIGN) }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use crate::backend::test::Host;
+    use mc_sgx_urts::{Enclave, EnclaveBuilder};
+    use mc_sgx_util::ResultInto;
+    use std::thread;
+    use test_enclave::{
+        ecall_alloc_cache_early_return, ecall_alloc_churn, ecall_alloc_free_blocks, ENCLAVE,
+        ENCLAVE_THREAD_CACHE,
+    };
+    use yare::parameterized;
+
+    /// The size of the blocks to measure the free heap with, larger than the
+    /// cached size classes
+    const PROBE_SIZE: usize = 4096;
+
+    /// The number of ecalls to make when checking for leaks. Each one caches
+    /// 16 blocks of 64 bytes, 1 KiB, so leaking them would take 2 MiB, twice
+    /// the test enclave's heap.
+    const ECALLS: usize = 2048;
+
+    /// The deallocations made to [`Counting`] by the current thread
+    #[thread_local]
+    static DEALLOCS: Cell<usize> = Cell::new(0);
+
+    /// Counts the deallocations made to the host's allocator
+    #[derive(Debug)]
+    struct Counting;
+
+    unsafe impl Backend for Counting {
+        const INIT: Self = Counting;
+
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            Host.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            DEALLOCS.set(DEALLOCS.get() + 1);
+            Host.dealloc(ptr, layout)
+        }
+    }
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).expect("Layout should be valid")
+    }
+
+    #[parameterized(
+    byte = {1, 1, Some(16)},
+    min_class = {16, 8, Some(16)},
+    rounded_up = {17, 1, Some(32)},
+    max_class = {256, 16, Some(256)},
+    too_large = {257, 8, None},
+    over_aligned = {8, 32, None},
+    )]
+    fn size_class_of_layout(size: usize, align: usize, expected: Option<usize>) {
+        let class = size_class(layout(size, align));
+
+        assert_eq!(class.map(|c| block_layout(c).size()), expected);
+    }
+
+    // Each test runs on its own thread, so starts with an empty cache
+    #[test]
+    fn freed_block_is_reused_by_thread() {
+        let cache = Cache::<Counting>::INIT;
+        let layout = layout(24, 8);
+
+        unsafe {
+            let first = cache.alloc(layout);
+            assert!(!first.is_null());
+            cache.dealloc(first, layout);
+
+            assert_eq!(cache.alloc(layout), first);
+            let other_thread = thread::spawn(move || cache.alloc(layout) as usize)
+                .join()
+                .expect("Thread should not panic");
+            assert_ne!(other_thread, first as usize);
+        }
+    }
+
+    #[test]
+    fn uncached_layouts_go_to_backend() {
+        let cache = Cache::<Counting>::INIT;
+
+        unsafe {
+            for layout in [layout(1000, 8), layout(8, 64)] {
+                let ptr = cache.alloc(layout);
+                assert!(!ptr.is_null());
+                cache.dealloc(ptr, layout);
+            }
+        }
+
+        assert_eq!(DEALLOCS.get(), 2);
+    }
+
+    #[test]
+    fn full_free_list_is_returned_in_a_batch() {
+        let cache = Cache::<Counting>::INIT;
+        let layout = layout(64, 8);
+
+        unsafe {
+            let blocks = (0..=MAX_CACHED)
+                .map(|_| cache.alloc(layout))
+                .collect::<std::vec::Vec<_>>();
+            for block in &blocks[..MAX_CACHED] {
+                cache.dealloc(*block, layout);
+            }
+            assert_eq!(DEALLOCS.get(), 0);
+
+            cache.dealloc(blocks[MAX_CACHED], layout);
+            assert_eq!(DEALLOCS.get(), BATCH);
+        }
+
+        cache.flush();
+        assert_eq!(DEALLOCS.get(), MAX_CACHED + 1);
+    }
+
+    #[test]
+    fn realloc_preserves_contents() {
+        let cache = Cache::<Counting>::INIT;
+
+        unsafe {
+            let ptr = cache.alloc(layout(20, 4));
+            ptr::copy_nonoverlapping(b"abcd".as_ptr(), ptr, 4);
+
+            assert_eq!(cache.realloc(ptr, layout(20, 4), 32), ptr);
+
+            let moved = cache.realloc(ptr, layout(32, 4), 1000);
+            assert!(!moved.is_null());
+            assert_eq!(core::slice::from_raw_parts(moved, 4), b"abcd");
+            cache.dealloc(moved, layout(1000, 4));
+        }
+
+        cache.flush();
+    }
+
+    #[test]
+    fn scope_flushes_after_panic() {
+        let cache = Cache::<Counting>::INIT;
+        let layout = layout(64, 8);
+
+        let result = std::panic::catch_unwind(|| {
+            cache.scope(|| unsafe {
+                let block = cache.alloc(layout);
+                cache.dealloc(block, layout);
+                panic!("Unwinding out of the scope");
+            })
+        });
+
+        assert!(result.is_err());
+        assert_eq!(DEALLOCS.get(), 1);
+        assert!(CACHE.iter().all(|free_list| free_list.len.get() == 0));
+    }
+
+    /// The number of blocks of [`PROBE_SIZE`] bytes that can be allocated from
+    /// the global allocator of `enclave`
+    fn free_blocks(enclave: &Enclave) -> usize {
+        let mut count = 0;
+        unsafe { ecall_alloc_free_blocks(*enclave.id(), PROBE_SIZE, &mut count) }
+            .into_result()
+            .unwrap();
+        count
+    }
+
+    #[parameterized(
+    allocator = {ENCLAVE},
+    thread_cache = {ENCLAVE_THREAD_CACHE},
+    )]
+    fn churn_from_enclave_threads(enclave: &[u8]) {
+        let enclave = EnclaveBuilder::from(enclave).create().unwrap();
+        let id = *enclave.id();
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    unsafe { ecall_alloc_churn(id, 64, 100) }
+                        .into_result()
+                        .unwrap()
+                });
+            }
+        });
+    }
+
+    // The test enclave's `TCSPolicy` is unbind, so the blocks cached during an
+    // ecall would leak without `with_thread_cache()` in `ecall_alloc_churn()`,
+    // using up the heap well before the last ecall.
+    #[test]
+    fn heap_does_not_grow_over_ecalls() {
+        let enclave = EnclaveBuilder::from(ENCLAVE_THREAD_CACHE).create().unwrap();
+        let id = *enclave.id();
+        let before = free_blocks(&enclave);
+
+        for _ in 0..ECALLS {
+            unsafe { ecall_alloc_churn(id, 64, 1) }
+                .into_result()
+                .unwrap();
+        }
+
+        assert!(before > 0);
+        assert_eq!(free_blocks(&enclave), before);
+    }
+
+    // `ecall_alloc_cache_early_return()` never flushes the cache itself, it
+    // returns early from `with_thread_cache()` with the blocks still cached.
+    #[test]
+    fn heap_does_not_grow_over_early_returns() {
+        let enclave = EnclaveBuilder::from(ENCLAVE_THREAD_CACHE).create().unwrap();
+        let id = *enclave.id();
+        let before = free_blocks(&enclave);
+
+        for _ in 0..ECALLS {
+            let mut reserved = 1;
+            unsafe { ecall_alloc_cache_early_return(id, 64, usize::MAX, &mut reserved) }
+                .into_result()
+                .unwrap();
+            assert_eq!(reserved, 0);
+        }
+
+        assert!(before > 0);
+        assert_eq!(free_blocks(&enclave), before);
+    }
+}
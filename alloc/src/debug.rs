@@ -0,0 +1,669 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! An allocator for finding memory safety bugs inside of an enclave
+//!
+//! Each allocation is laid out as:
+//!
+//! ```text
+//! [Header][padding][offset][front canary][user memory][back canary]
+//! ```
+//!
+//! The `offset` is the distance from the start of the [`Header`] to the user
+//! memory, it allows finding the [`Header`] without trusting the [`Layout`]
+//! given to `dealloc()`.
+
+use crate::spin::SpinLock;
+use crate::{oom, Allocator};
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::mem;
+use core::ptr;
+use mc_sgx_core_types::Error;
+use mc_sgx_io::{FlushingWriter, WriteBuffer};
+
+/// The byte pattern written before and after each allocation
+const CANARY: [u8; 8] = [0xFD; 8];
+
+/// The byte written to newly allocated memory, so that reads of uninitialized
+/// memory are recognizable
+const ALLOCATED: u8 = 0xCD;
+
+/// The byte written to freed memory, so that use after free is recognizable
+const FREED: u8 = 0xDD;
+
+/// The number of outstanding allocations copied out of the list at a time,
+/// when writing them to the host
+const LISTING_CHUNK: usize = 32;
+
+/// Bookkeeping for an outstanding allocation
+#[repr(C)]
+struct Header {
+    /// The size requested by the caller
+    size: usize,
+    /// The alignment requested by the caller
+    align: usize,
+    /// The previously made outstanding allocation
+    previous: *mut Header,
+    /// The next made outstanding allocation
+    next: *mut Header,
+}
+
+/// An outstanding allocation, as copied out of the list
+#[derive(Clone, Copy)]
+struct Outstanding {
+    /// The caller's memory
+    ptr: *mut u8,
+    /// The size requested by the caller
+    size: usize,
+    /// The alignment requested by the caller
+    align: usize,
+}
+
+impl Outstanding {
+    /// A placeholder for filling the chunks
+    const EMPTY: Self = Self {
+        ptr: ptr::null_mut(),
+        size: 0,
+        align: 0,
+    };
+}
+
+/// A corruption detected when deallocating
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Corruption {
+    /// The allocation was already freed
+    DoubleFree,
+    /// The bytes immediately before the allocation were overwritten
+    FrontCanary,
+    /// The bytes immediately after the allocation were overwritten
+    BackCanary,
+    /// The [`Layout`] given to `dealloc()` differs from the one given to
+    /// `alloc()`
+    LayoutMismatch {
+        /// The layout given to `alloc()`
+        allocated: Layout,
+    },
+}
+
+/// An allocator which checks for heap misuse, for debugging enclaves.
+///
+/// Each allocation is made from an inner allocator, the [`Allocator`] by
+/// default, with additional room for bookkeeping. The [`DebugAllocator`]:
+///
+/// - Surrounds each allocation with canaries. The canaries are verified when
+///   the allocation is freed, catching buffer overflows and underflows.
+/// - Fills new allocations with `0xCD` and freed allocations with `0xDD`,
+///   making uninitialized reads and use after free easier to recognize.
+/// - Overwrites the front canary of freed allocations, catching double frees
+///   as long as the memory hasn't been reused.
+/// - Verifies that the [`Layout`] given to `dealloc()` matches the one the
+///   memory was allocated with.
+/// - Keeps a list of the outstanding allocations, which can be written to the
+///   host with [`DebugAllocator::dump_outstanding()`] to find leaks.
+///
+/// When a corruption is detected, it is reported to the host's stderr sink
+/// and the enclave is aborted.
+///
+/// The bookkeeping makes each allocation larger and slower, and all
+/// allocations contend on one lock, so this is only meant for debugging.
+///
+/// # Examples
+///
+/// ```ignore
+/// mc_sgx_alloc::allocator!(ALLOCATOR, debug);
+///
+/// // From an ecall
+/// ALLOCATOR.dump_outstanding().unwrap();
+/// ```
+pub struct DebugAllocator<A = Allocator> {
+    inner: A,
+    lock: SpinLock,
+    /// The most recent outstanding allocation, guarded by `lock`
+    last: UnsafeCell<*mut Header>,
+}
+
+/// SAFETY: The list of outstanding allocations is only accessed while holding
+/// the lock.
+unsafe impl<A: Send> Send for DebugAllocator<A> {}
+
+/// SAFETY: The list of outstanding allocations is only accessed while holding
+/// the lock.
+unsafe impl<A: Sync> Sync for DebugAllocator<A> {}
+
+impl<A> DebugAllocator<A> {
+    /// Create a new [`DebugAllocator`] making its allocations from `inner`
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            lock: SpinLock::new(),
+            last: UnsafeCell::new(ptr::null_mut()),
+        }
+    }
+
+    /// Write the outstanding allocations to the hosts stderr sink.
+    ///
+    /// Each allocation is listed with its address, size and alignment,
+    /// followed by the totals. This is meant to be called from an ecall, for
+    /// instance prior to destroying the enclave, to find leaked allocations.
+    ///
+    /// The allocations are copied out of the list a chunk at a time and
+    /// written without holding the lock. Allocations made, or freed, by other
+    /// threads while the list is being written may be missed or listed twice.
+    ///
+    /// # Errors
+    /// When writing to the hosts stderr sink fails.
+    pub fn dump_outstanding(&self) -> Result<(), Error> {
+        let mut writer = FlushingWriter::new(mc_sgx_io::stderr_write_all);
+        // Formatting addresses and integers doesn't fail, so any failure is
+        // from the sink, which `flush()` reports.
+        let _ = self.write_outstanding(&mut writer);
+        writer.flush()
+    }
+
+    /// Write the outstanding allocations to `writer`
+    ///
+    /// The lock is only held while copying each chunk out of the list, so
+    /// `writer` may allocate, or make ocalls.
+    fn write_outstanding(&self, writer: &mut impl Write) -> fmt::Result {
+        writeln!(writer, "outstanding allocations:")?;
+        let (mut count, mut bytes) = (0, 0);
+        let mut chunk = [Outstanding::EMPTY; LISTING_CHUNK];
+        loop {
+            let copied = self.copy_outstanding(count, &mut chunk);
+            for &Outstanding { ptr, size, align } in &chunk[..copied] {
+                writeln!(writer, "  {ptr:p}: {size} bytes, align {align}")?;
+                bytes += size;
+            }
+            count += copied;
+            if copied < chunk.len() {
+                break;
+            }
+        }
+        writeln!(writer, "{count} outstanding allocations, {bytes} bytes")
+    }
+
+    /// Copy the outstanding allocations into `chunk`, most recent first,
+    /// after skipping the first `skip` of them
+    ///
+    /// # Returns
+    /// The number of allocations copied, less than the length of `chunk` once
+    /// the end of the list is reached.
+    fn copy_outstanding(&self, skip: usize, chunk: &mut [Outstanding]) -> usize {
+        let _guard = self.lock.lock();
+        // SAFETY: The lock is held, so the list is consistent
+        let mut header = unsafe { *self.last.get() };
+        let mut skipped = 0;
+        let mut copied = 0;
+        while !header.is_null() && copied < chunk.len() {
+            // SAFETY: Only outstanding allocations are in the list
+            let &Header {
+                size,
+                align,
+                previous,
+                ..
+            } = unsafe { &*header };
+            if skipped < skip {
+                skipped += 1;
+            } else {
+                chunk[copied] = Outstanding {
+                    ptr: user_ptr(header, align),
+                    size,
+                    align,
+                };
+                copied += 1;
+            }
+            header = previous;
+        }
+        copied
+    }
+
+    /// Add `header` to the list of outstanding allocations
+    ///
+    /// # Safety
+    /// `header` must be a valid, newly written, [`Header`].
+    unsafe fn link(&self, header: *mut Header) {
+        let _guard = self.lock.lock();
+        let last = self.last.get();
+        (*header).previous = *last;
+        (*header).next = ptr::null_mut();
+        if let Some(previous) = (*last).as_mut() {
+            previous.next = header;
+        }
+        *last = header;
+    }
+
+    /// Remove `header` from the list of outstanding allocations
+    ///
+    /// # Safety
+    /// `header` must be in the list of outstanding allocations.
+    unsafe fn unlink(&self, header: *mut Header) {
+        let _guard = self.lock.lock();
+        let Header { previous, next, .. } = *header;
+        if let Some(previous) = previous.as_mut() {
+            previous.next = next;
+        }
+        match next.as_mut() {
+            Some(next) => next.previous = previous,
+            None => *self.last.get() = previous,
+        }
+    }
+}
+
+impl<A> fmt::Debug for DebugAllocator<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DebugAllocator").finish_non_exhaustive()
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for DebugAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some((outer, offset)) = outer_layout(layout) else {
+            return ptr::null_mut();
+        };
+        let header = self.inner.alloc(outer) as *mut Header;
+        if header.is_null() {
+            return ptr::null_mut();
+        }
+
+        header.write(Header {
+            size: layout.size(),
+            align: layout.align(),
+            previous: ptr::null_mut(),
+            next: ptr::null_mut(),
+        });
+        let user = (header as *mut u8).add(offset);
+        (user.sub(CANARY.len() + mem::size_of::<usize>()) as *mut usize).write(offset);
+        user.sub(CANARY.len())
+            .copy_from_nonoverlapping(CANARY.as_ptr(), CANARY.len());
+        user.write_bytes(ALLOCATED, layout.size());
+        user.add(layout.size())
+            .copy_from_nonoverlapping(CANARY.as_ptr(), CANARY.len());
+
+        self.link(header);
+        user
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let header = match check(ptr, layout) {
+            Ok(header) => header,
+            Err(corruption) => report_corruption(ptr, layout, corruption),
+        };
+
+        self.unlink(header);
+        // The front canary is poisoned too, marking the allocation as freed
+        // for `check()`
+        ptr.sub(CANARY.len())
+            .write_bytes(FREED, CANARY.len() + layout.size());
+        let (outer, _) = outer_layout(layout).expect("Layout was valid when allocated");
+        self.inner.dealloc(header as *mut u8, outer);
+    }
+}
+
+/// The layout of the allocation from the inner allocator for the caller's
+/// `layout`, and the offset of the caller's memory within it.
+///
+/// # Returns
+/// `None` if the allocation would be too large.
+fn outer_layout(layout: Layout) -> Option<(Layout, usize)> {
+    let offset = user_offset(layout.align())?;
+    let size = offset
+        .checked_add(layout.size())?
+        .checked_add(CANARY.len())?;
+    let align = layout.align().max(mem::align_of::<Header>());
+    let outer = Layout::from_size_align(size, align).ok()?;
+    Some((outer, offset))
+}
+
+/// The offset of the caller's memory from the [`Header`] for an allocation
+/// aligned to `align`.
+///
+/// # Returns
+/// `None` if the offset would overflow.
+fn user_offset(align: usize) -> Option<usize> {
+    let align = align.max(mem::align_of::<Header>());
+    let bookkeeping = mem::size_of::<Header>() + mem::size_of::<usize>() + CANARY.len();
+    Some(bookkeeping.checked_add(align - 1)? & !(align - 1))
+}
+
+/// The caller's memory for the allocation with `header`
+fn user_ptr(header: *mut Header, align: usize) -> *mut u8 {
+    let offset = user_offset(align).expect("Alignment was valid when allocated");
+    (header as *mut u8).wrapping_add(offset)
+}
+
+/// Check the bookkeeping of the allocation at `ptr`
+///
+/// # Returns
+/// The [`Header`] of the allocation.
+///
+/// # Errors
+/// When `ptr` was already freed, the canaries were overwritten or `layout`
+/// isn't the one `ptr` was allocated with.
+///
+/// # Safety
+/// `ptr` must have been allocated by a [`DebugAllocator`], and if freed, its
+/// memory must still be readable.
+unsafe fn check(ptr: *mut u8, layout: Layout) -> Result<*mut Header, Corruption> {
+    let front = ptr.sub(CANARY.len());
+    match (front as *const [u8; 8]).read_unaligned() {
+        CANARY => {}
+        freed if freed == [FREED; 8] => return Err(Corruption::DoubleFree),
+        _ => return Err(Corruption::FrontCanary),
+    }
+
+    let offset = (front.sub(mem::size_of::<usize>()) as *const usize).read();
+    let header = ptr.sub(offset) as *mut Header;
+    let (size, align) = ((*header).size, (*header).align);
+    if size != layout.size() || align != layout.align() {
+        return Err(Corruption::LayoutMismatch {
+            allocated: Layout::from_size_align_unchecked(size, align),
+        });
+    }
+
+    if (ptr.add(size) as *const [u8; 8]).read_unaligned() != CANARY {
+        return Err(Corruption::BackCanary);
+    }
+
+    Ok(header)
+}
+
+/// Report `corruption` of the allocation at `ptr` to the host and abort the
+/// enclave.
+fn report_corruption(ptr: *mut u8, layout: Layout, corruption: Corruption) -> ! {
+    let mut buffer = WriteBuffer::new_lossy();
+    // Ignore the results, the buffer is lossy so the worst case is a truncated
+    // report, and there is nothing more we can do if the host doesn't receive
+    // it.
+    let _ = write_corruption(&mut buffer, ptr, layout, corruption);
+    let _ = mc_sgx_io::stderr_write_all(buffer.as_ref());
+
+    oom::abort()
+}
+
+/// Format the report of `corruption` into `buffer`
+fn write_corruption(
+    buffer: &mut impl Write,
+    ptr: *mut u8,
+    layout: Layout,
+    corruption: Corruption,
+) -> fmt::Result {
+    write!(
+        buffer,
+        "heap corruption in deallocation of {ptr:p} ({} bytes, align {}): ",
+        layout.size(),
+        layout.align()
+    )?;
+    match corruption {
+        Corruption::DoubleFree => writeln!(buffer, "the allocation was already freed"),
+        Corruption::FrontCanary => writeln!(buffer, "memory before the allocation was overwritten"),
+        Corruption::BackCanary => writeln!(buffer, "memory after the allocation was overwritten"),
+        Corruption::LayoutMismatch { allocated } => writeln!(
+            buffer,
+            "allocated with {} bytes, align {}",
+            allocated.size(),
+            allocated.align()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use std::alloc::System;
+    use std::string::String;
+    use yare::parameterized;
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).expect("Layout should be valid")
+    }
+
+    #[parameterized(
+    byte = {1, 1},
+    word = {8, 8},
+    odd = {13, 4},
+    over_aligned = {100, 64},
+    page = {10, 4096},
+    )]
+    fn allocation_is_aligned_and_checked(size: usize, align: usize) {
+        let allocator = DebugAllocator::new(System);
+        let layout = layout(size, align);
+
+        unsafe {
+            let ptr = allocator.alloc(layout);
+
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % align, 0);
+            assert!(std::slice::from_raw_parts(ptr, size)
+                .iter()
+                .all(|b| *b == ALLOCATED));
+            assert!(check(ptr, layout).is_ok());
+
+            allocator.dealloc(ptr, layout);
+        }
+    }
+
+    #[test]
+    fn overflow_overwrites_back_canary() {
+        let allocator = DebugAllocator::new(System);
+        let layout = layout(16, 8);
+
+        unsafe {
+            let ptr = allocator.alloc(layout);
+            ptr.add(16).write(0);
+
+            // The allocation is leaked, deallocating it would abort
+
+            assert_eq!(check(ptr, layout), Err(Corruption::BackCanary));
+        }
+    }
+
+    #[test]
+    fn underflow_overwrites_front_canary() {
+        let allocator = DebugAllocator::new(System);
+        let layout = layout(16, 8);
+
+        unsafe {
+            let ptr = allocator.alloc(layout);
+            ptr.sub(1).write(0);
+
+            // The allocation is leaked, deallocating it would abort
+
+            assert_eq!(check(ptr, layout), Err(Corruption::FrontCanary));
+        }
+    }
+
+    /// Never frees, so that the bookkeeping of freed allocations can still be
+    /// checked
+    struct Leaking;
+
+    unsafe impl GlobalAlloc for Leaking {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+    }
+
+    #[test]
+    fn freed_allocation_is_a_double_free() {
+        let allocator = DebugAllocator::new(Leaking);
+        let (first, second) = (layout(16, 8), layout(32, 8));
+
+        unsafe {
+            let first_ptr = allocator.alloc(first);
+            let second_ptr = allocator.alloc(second);
+            allocator.dealloc(first_ptr, first);
+
+            assert_eq!(check(first_ptr, first), Err(Corruption::DoubleFree));
+            assert!(std::slice::from_raw_parts(first_ptr, 16)
+                .iter()
+                .all(|b| *b == FREED));
+            assert!(check(second_ptr, second).is_ok());
+
+            allocator.dealloc(second_ptr, second);
+        }
+    }
+
+    #[test]
+    fn mismatched_layout() {
+        let allocator = DebugAllocator::new(System);
+        let allocated = layout(16, 8);
+
+        unsafe {
+            let ptr = allocator.alloc(allocated);
+
+            assert_eq!(
+                check(ptr, layout(32, 8)),
+                Err(Corruption::LayoutMismatch { allocated })
+            );
+            assert_eq!(
+                check(ptr, layout(16, 16)),
+                Err(Corruption::LayoutMismatch { allocated })
+            );
+
+            allocator.dealloc(ptr, allocated);
+        }
+    }
+
+    #[test]
+    fn outstanding_allocations_are_listed() {
+        let allocator = DebugAllocator::new(System);
+        let (first, second, third) = (layout(10, 1), layout(20, 8), layout(30, 64));
+
+        let mut listing = String::new();
+        unsafe {
+            let first_ptr = allocator.alloc(first);
+            let second_ptr = allocator.alloc(second);
+            let third_ptr = allocator.alloc(third);
+            allocator.dealloc(second_ptr, second);
+
+            allocator
+                .write_outstanding(&mut listing)
+                .expect("Shouldn't fail to write to a String");
+
+            assert_eq!(
+                listing,
+                std::format!(
+                    "outstanding allocations:\n  {third_ptr:p}: 30 bytes, align 64\n  {first_ptr:p}: 10 bytes, align 1\n2 outstanding allocations, 40 bytes\n"
+                )
+            );
+
+            allocator.dealloc(first_ptr, first);
+            allocator.dealloc(third_ptr, third);
+        }
+    }
+
+    #[test]
+    fn outstanding_allocations_are_listed_across_chunks() {
+        let allocator = DebugAllocator::new(System);
+        let layout = layout(8, 8);
+        let count = LISTING_CHUNK * 2 + 1;
+
+        let mut listing = String::new();
+        unsafe {
+            let ptrs = (0..count)
+                .map(|_| allocator.alloc(layout))
+                .collect::<std::vec::Vec<_>>();
+
+            allocator
+                .write_outstanding(&mut listing)
+                .expect("Shouldn't fail to write to a String");
+
+            let mut expected = String::from("outstanding allocations:\n");
+            for &ptr in ptrs.iter().rev() {
+                expected += &std::format!("  {ptr:p}: 8 bytes, align 8\n");
+            }
+            expected += &std::format!("{count} outstanding allocations, {} bytes\n", count * 8);
+            assert_eq!(listing, expected);
+
+            for ptr in ptrs {
+                allocator.dealloc(ptr, layout);
+            }
+        }
+    }
+
+    /// Allocates from the [`DebugAllocator`] being listed on every write,
+    /// which would never finish if the lock were held while writing
+    struct AllocatingWriter<'a> {
+        allocator: &'a DebugAllocator<System>,
+        listing: String,
+    }
+
+    impl Write for AllocatingWriter<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let layout = layout(1, 1);
+            unsafe {
+                let ptr = self.allocator.alloc(layout);
+                self.allocator.dealloc(ptr, layout);
+            }
+            self.listing.write_str(s)
+        }
+    }
+
+    #[test]
+    fn writer_can_allocate_while_listing() {
+        let allocator = DebugAllocator::new(System);
+        let layout = layout(24, 8);
+        let mut writer = AllocatingWriter {
+            allocator: &allocator,
+            listing: String::new(),
+        };
+
+        unsafe {
+            let ptr = allocator.alloc(layout);
+
+            allocator
+                .write_outstanding(&mut writer)
+                .expect("Shouldn't fail to write to a String");
+
+            assert_eq!(
+                writer.listing,
+                std::format!(
+                    "outstanding allocations:\n  {ptr:p}: 24 bytes, align 8\n1 outstanding allocations, 24 bytes\n"
+                )
+            );
+
+            allocator.dealloc(ptr, layout);
+        }
+    }
+
+    #[test]
+    fn no_outstanding_allocations() {
+        let allocator = DebugAllocator::new(System);
+        let mut listing = String::new();
+
+        allocator
+            .write_outstanding(&mut listing)
+            .expect("Shouldn't fail to write to a String");
+
+        assert_eq!(
+            listing,
+            "outstanding allocations:\n0 outstanding allocations, 0 bytes\n"
+        );
+    }
+
+    #[test]
+    fn corruption_report() {
+        let mut buffer = WriteBuffer::new();
+        let ptr = 0x1000 as *mut u8;
+
+        write_corruption(
+            &mut buffer,
+            ptr,
+            layout(8, 8),
+            Corruption::LayoutMismatch {
+                allocated: layout(16, 8),
+            },
+        )
+        .expect("Report should fit");
+
+        let contents: &str = buffer.as_ref();
+        assert_eq!(
+            contents,
+            "heap corruption in deallocation of 0x1000 (8 bytes, align 8): allocated with 16 bytes, align 8\n"
+        );
+    }
+}
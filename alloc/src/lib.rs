@@ -1,5 +1,6 @@
 // Copyright (c) 2022 The MobileCoin Foundation
 
+#![feature(thread_local)]
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs, missing_debug_implementations)]
 #![no_std]
@@ -7,24 +8,73 @@
 extern crate alloc;
 
 mod allocator;
+mod arena;
+mod backend;
+mod bump;
+mod cache;
+#[cfg(feature = "debug")]
+mod debug;
+mod limit;
+mod oom;
+mod slab;
+mod spin;
+mod stats;
+#[cfg(feature = "untrusted")]
+mod untrusted;
 
 pub use crate::allocator::Allocator;
+pub use crate::backend::{Backend, BackendAllocator};
+pub use crate::bump::Bump;
+pub use crate::cache::{flush_thread_cache, with_thread_cache, ThreadCache};
+#[cfg(feature = "debug")]
+pub use crate::debug::DebugAllocator;
+pub use crate::limit::with_alloc_limit;
+pub use crate::oom::alloc_error_handler;
+pub use crate::slab::Slab;
+#[cfg(feature = "stats")]
+pub use crate::stats::{stats, Stats};
+#[cfg(feature = "untrusted")]
+pub use crate::untrusted::{Plain, UntrustedAllocator, UntrustedBox, UntrustedVec};
 
 /// Defines a global allocator for use in an SGX enclave.
 ///
 /// This should only be used in one place in the enclave binary.
 ///
-/// The macro takes one argument, the name of the static allocator object.
+/// The macro takes the name of the static allocator object. It optionally
+/// takes either `debug` to use a `DebugAllocator`, which checks for heap
+/// misuse and needs the `debug` feature, or a [`Backend`] type to use a
+/// [`BackendAllocator`], instead of the [`Allocator`].
 ///
 /// # Example
 ///
 /// ```
 /// mc_sgx_alloc::allocator!(ALLOCATOR_NAME);
 /// ```
+///
+/// For debugging:
+///
+/// ```ignore
+/// mc_sgx_alloc::allocator!(ALLOCATOR_NAME, debug);
+/// ```
+///
+/// With a 4 MiB slab allocator backend:
+///
+/// ```ignore
+/// mc_sgx_alloc::allocator!(ALLOCATOR_NAME, mc_sgx_alloc::Slab<{ 4 << 20 }>);
+/// ```
 #[macro_export]
 macro_rules! allocator {
     ($name:ident) => {
         #[global_allocator]
         static $name: $crate::Allocator = $crate::Allocator;
     };
+    ($name:ident, debug) => {
+        #[global_allocator]
+        static $name: $crate::DebugAllocator = $crate::DebugAllocator::new($crate::Allocator);
+    };
+    ($name:ident, $backend:ty) => {
+        #[global_allocator]
+        static $name: $crate::BackendAllocator<$backend> =
+            $crate::BackendAllocator::new(<$backend as $crate::Backend>::INIT);
+    };
 }
@@ -0,0 +1,196 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Scoped per thread limits on the bytes allocated
+
+use core::cell::Cell;
+
+/// The limit of the innermost [`with_alloc_limit()`] of the current thread,
+/// `None` when unlimited
+#[thread_local]
+static LIMIT: Cell<Option<Limit>> = Cell::new(None);
+
+/// The budget of a [`with_alloc_limit()`] scope
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Limit {
+    /// The most bytes the scope may have allocated at once
+    bytes: usize,
+    /// The bytes currently allocated within the scope
+    used: usize,
+}
+
+/// Restores the enclosing limit when a scope ends, including by unwinding
+struct Scope {
+    outer: Option<Limit>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let inner = LIMIT.get().map_or(0, |limit| limit.used);
+        // Memory the scope still has allocated counts against the enclosing
+        // limit
+        let outer = self.outer.map(|mut limit| {
+            limit.used = limit.used.saturating_add(inner).min(limit.bytes);
+            limit
+        });
+        LIMIT.set(outer);
+    }
+}
+
+/// Run `f` with the allocations of the current thread limited to `bytes`
+///
+/// While `f` runs, allocations from the global allocator which would take the
+/// bytes allocated within `f`, less those freed, over `bytes` fail by
+/// returning null. Other threads are unaffected.
+///
+/// Nested limits can't exceed the remaining budget of the enclosing limit.
+///
+/// Fallible allocation, like [`Vec::try_reserve()`](alloc::vec::Vec::try_reserve),
+/// is needed to recover from the limit. An infallible allocation, like
+/// [`Vec::with_capacity()`](alloc::vec::Vec::with_capacity), which exceeds the
+/// limit goes to the alloc error handler, aborting the enclave.
+///
+/// Only the [`Allocator`](crate::Allocator) and the
+/// [`BackendAllocator`](crate::BackendAllocator) enforce the limit.
+///
+/// # Examples
+///
+/// Rejecting a length from the host which is too large:
+///
+/// ```ignore
+/// let buffer = mc_sgx_alloc::with_alloc_limit(1 << 16, || {
+///     let mut buffer = Vec::new();
+///     buffer.try_reserve_exact(host_len).ok()?;
+///     Some(buffer)
+/// });
+/// ```
+pub fn with_alloc_limit<R>(bytes: usize, f: impl FnOnce() -> R) -> R {
+    let outer = LIMIT.get();
+    let bytes = outer.map_or(bytes, |limit| bytes.min(limit.bytes - limit.used));
+    LIMIT.set(Some(Limit { bytes, used: 0 }));
+    let _scope = Scope { outer };
+    f()
+}
+
+/// Charge `size` bytes to the current thread's limit
+///
+/// Returns false, without charging, when the limit would be exceeded.
+pub(crate) fn try_charge(size: usize) -> bool {
+    let Some(mut limit) = LIMIT.get() else {
+        return true;
+    };
+    match limit.used.checked_add(size) {
+        Some(used) if used <= limit.bytes => {
+            limit.used = used;
+            LIMIT.set(Some(limit));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Refund `size` bytes to the current thread's limit
+///
+/// Memory allocated prior to the limit may be freed within it, so the refund
+/// stops at nothing used.
+pub(crate) fn refund(size: usize) {
+    if let Some(mut limit) = LIMIT.get() {
+        limit.used = limit.used.saturating_sub(size);
+        LIMIT.set(Some(limit));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use mc_sgx_urts::EnclaveBuilder;
+    use mc_sgx_util::ResultInto;
+    use test_enclave::{ecall_alloc_limit, ENCLAVE};
+    use yare::parameterized;
+
+    fn used() -> Option<usize> {
+        LIMIT.get().map(|limit| limit.used)
+    }
+
+    // Each test runs on its own thread, so starts without a limit
+    #[test]
+    fn unlimited_without_scope() {
+        assert!(try_charge(usize::MAX));
+        refund(usize::MAX);
+
+        assert_eq!(LIMIT.get(), None);
+    }
+
+    #[test]
+    fn charges_up_to_limit() {
+        with_alloc_limit(100, || {
+            assert!(try_charge(60));
+            assert!(!try_charge(41));
+            assert!(try_charge(40));
+            assert!(!try_charge(1));
+
+            refund(50);
+            assert!(try_charge(50));
+        });
+
+        assert_eq!(LIMIT.get(), None);
+    }
+
+    #[test]
+    fn refund_of_memory_from_before_the_limit() {
+        with_alloc_limit(100, || {
+            refund(1000);
+
+            assert_eq!(used(), Some(0));
+            assert!(!try_charge(101));
+        });
+    }
+
+    #[test]
+    fn nested_limit_within_outer_budget() {
+        with_alloc_limit(100, || {
+            assert!(try_charge(70));
+
+            with_alloc_limit(1000, || {
+                assert!(!try_charge(31));
+                assert!(try_charge(20));
+            });
+
+            assert_eq!(used(), Some(90));
+        });
+    }
+
+    #[test]
+    fn limit_restored_after_panic() {
+        with_alloc_limit(100, || {
+            let result = std::panic::catch_unwind(|| {
+                with_alloc_limit(50, || {
+                    assert!(try_charge(10));
+                    panic!("Unwinding out of the limit");
+                })
+            });
+
+            assert!(result.is_err());
+            assert_eq!(used(), Some(10));
+        });
+    }
+
+    #[parameterized(
+    within = {1024, 1000, true},
+    exact = {1024, 1024, true},
+    over = {1024, 1025, false},
+    huge = {1024, usize::MAX / 2, false},
+    )]
+    fn limit_in_enclave(limit: usize, size: usize, expected: bool) {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        let mut allocated = 0;
+        unsafe { ecall_alloc_limit(id, limit, size, &mut allocated) }
+            .into_result()
+            .unwrap();
+
+        assert_eq!(allocated != 0, expected);
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Handling of failed allocations
+
+use core::alloc::Layout;
+#[cfg(feature = "oom-report")]
+use {
+    core::fmt::{self, Write},
+    mc_sgx_io::WriteBuffer,
+};
+
+/// Report a failed allocation to the host and abort the enclave.
+///
+/// This is meant to be used as the enclave's `#[alloc_error_handler]`. With
+/// the `oom-report` feature, the size and alignment of the failed allocation
+/// are written to the hosts stderr sink, along with the heap usage when the
+/// `stats` feature is also enabled. The enclave is then aborted with the SGX
+/// SDK `abort()`, the same as `mc-sgx-panic` does, marking the enclave as
+/// crashed.
+///
+/// The report is formatted on the stack, the heap can't be relied upon once
+/// an allocation has failed.
+///
+/// # Arguments
+/// * `layout` - The layout of the allocation that failed
+///
+/// # Examples
+///
+/// ```ignore
+/// #![feature(alloc_error_handler)]
+///
+/// #[alloc_error_handler]
+/// fn alloc_error(layout: core::alloc::Layout) -> ! {
+///     mc_sgx_alloc::alloc_error_handler(layout)
+/// }
+/// ```
+pub fn alloc_error_handler(layout: Layout) -> ! {
+    #[cfg(feature = "oom-report")]
+    report_alloc_error(layout);
+    #[cfg(not(feature = "oom-report"))]
+    let _ = layout;
+
+    abort()
+}
+
+/// Write the report of a failed allocation to the hosts stderr sink
+#[cfg(feature = "oom-report")]
+fn report_alloc_error(layout: Layout) {
+    let mut buffer = WriteBuffer::new_lossy();
+    // Ignore the results, the buffer is lossy so the worst case is a truncated
+    // report, and there is nothing more we can do if the host doesn't receive
+    // it.
+    let _ = write_alloc_error(&mut buffer, layout, heap_usage());
+    let _ = mc_sgx_io::stderr_write_all(buffer.as_ref());
+}
+
+/// The live and peak bytes
+#[cfg(all(feature = "oom-report", feature = "stats"))]
+fn heap_usage() -> Option<(usize, usize)> {
+    Some((crate::stats::live_bytes(), crate::stats::peak_bytes()))
+}
+
+/// The heap usage isn't tracked without the `stats` feature
+#[cfg(all(feature = "oom-report", not(feature = "stats")))]
+fn heap_usage() -> Option<(usize, usize)> {
+    None
+}
+
+/// Abort the enclave with the SGX SDK `abort()`, marking it as crashed.
+pub(crate) fn abort() -> ! {
+    extern "C" {
+        fn abort() -> !;
+    }
+
+    unsafe { abort() }
+}
+
+/// Format the report of a failed allocation into `buffer`
+///
+/// # Arguments
+/// * `buffer` - The buffer to format the report into
+/// * `layout` - The layout of the allocation that failed
+/// * `heap_usage` - The bytes allocated at the time of the failure and the
+///   most bytes that have been allocated at one time, if they're tracked
+#[cfg(feature = "oom-report")]
+fn write_alloc_error(
+    buffer: &mut impl Write,
+    layout: Layout,
+    heap_usage: Option<(usize, usize)>,
+) -> fmt::Result {
+    write!(
+        buffer,
+        "memory allocation of {} bytes with alignment {} failed",
+        layout.size(),
+        layout.align()
+    )?;
+    if let Some((live_bytes, peak_bytes)) = heap_usage {
+        write!(
+            buffer,
+            ", heap usage: {live_bytes} bytes live, {peak_bytes} bytes peak"
+        )?;
+    }
+    writeln!(buffer)
+}
+
+#[cfg(all(test, feature = "oom-report"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report_has_layout_and_heap_usage() {
+        let mut buffer = WriteBuffer::new();
+        let layout = Layout::from_size_align(1024, 16).expect("Layout should be valid");
+
+        write_alloc_error(&mut buffer, layout, Some((4096, 8192))).expect("Report should fit");
+
+        let contents: &str = buffer.as_ref();
+        assert_eq!(
+            contents,
+            "memory allocation of 1024 bytes with alignment 16 failed, heap usage: 4096 bytes live, 8192 bytes peak\n"
+        );
+    }
+
+    #[test]
+    fn report_without_heap_usage() {
+        let mut buffer = WriteBuffer::new();
+        let layout = Layout::from_size_align(1024, 16).expect("Layout should be valid");
+
+        write_alloc_error(&mut buffer, layout, None).expect("Report should fit");
+
+        let contents: &str = buffer.as_ref();
+        assert_eq!(
+            contents,
+            "memory allocation of 1024 bytes with alignment 16 failed\n"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! A size class slab allocator backend
+
+use crate::allocator::SdkHeap;
+use crate::arena::Arena;
+use crate::backend::realloc_by_copy;
+use crate::spin::SpinLock;
+use crate::Backend;
+use core::alloc::Layout;
+use core::cell::UnsafeCell;
+use core::ptr;
+
+/// The smallest size class, large enough to hold a [`Block`]
+const MIN_CLASS: usize = 16;
+
+/// The largest size class
+const MAX_CLASS: usize = 4096;
+
+/// The number of size classes, the powers of two from [`MIN_CLASS`] to
+/// [`MAX_CLASS`]
+const CLASSES: usize = (MAX_CLASS.trailing_zeros() - MIN_CLASS.trailing_zeros() + 1) as usize;
+
+/// A free block of a size class
+struct Block {
+    next: *mut Block,
+}
+
+/// The free blocks of one size class
+#[derive(Debug)]
+struct FreeList {
+    lock: SpinLock,
+    /// The most recently freed block, guarded by `lock`
+    head: UnsafeCell<*mut Block>,
+}
+
+impl FreeList {
+    /// An empty list, for initializing the array of free lists
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Self = Self {
+        lock: SpinLock::new(),
+        head: UnsafeCell::new(ptr::null_mut()),
+    };
+
+    /// Take a free block, if any
+    fn pop(&self) -> *mut u8 {
+        let _guard = self.lock.lock();
+        // SAFETY: The lock is held and the blocks in the list are unused
+        unsafe {
+            let head = *self.head.get();
+            if !head.is_null() {
+                *self.head.get() = (*head).next;
+            }
+            head as *mut u8
+        }
+    }
+
+    /// Return `block` to the list
+    ///
+    /// # Safety
+    ///
+    /// `block` must be an unused block of this list's size class.
+    unsafe fn push(&self, block: *mut u8) {
+        let block = block as *mut Block;
+        let _guard = self.lock.lock();
+        (*block).next = *self.head.get();
+        *self.head.get() = block;
+    }
+}
+
+/// A [`Backend`] which carves blocks of power of two size classes, from 16 to
+/// 4096 bytes, out of a fixed region of `SIZE` bytes
+///
+/// Freed blocks are kept on a free list per size class, each with its own
+/// lock, so threads allocating different sizes don't contend with each other.
+/// Blocks are never returned to the region, nor split or merged between size
+/// classes.
+///
+/// Allocations larger than 4096 bytes, and those made once the region is used
+/// up, come from the `F` backend, the SGX SDK's heap by default.
+///
+/// # Example
+///
+/// ```ignore
+/// // 4 MiB of slabs, the region counts against the enclave's size
+/// mc_sgx_alloc::allocator!(ALLOCATOR, mc_sgx_alloc::Slab<{ 4 << 20 }>);
+/// ```
+#[derive(Debug)]
+pub struct Slab<const SIZE: usize, F = SdkHeap> {
+    arena: Arena<SIZE>,
+    free_lists: [FreeList; CLASSES],
+    fallback: F,
+}
+
+// SAFETY: The free lists are only accessed while holding their lock
+unsafe impl<const SIZE: usize, F: Sync> Sync for Slab<SIZE, F> {}
+
+unsafe impl<const SIZE: usize, F: Backend> Backend for Slab<SIZE, F> {
+    const INIT: Self = Self {
+        arena: Arena::new(),
+        free_lists: [FreeList::EMPTY; CLASSES],
+        fallback: F::INIT,
+    };
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(class) = size_class(layout) else {
+            return self.fallback.alloc(layout);
+        };
+
+        let block = self.free_lists[class].pop();
+        if !block.is_null() {
+            return block;
+        }
+
+        let size = class_size(class);
+        let block = self
+            .arena
+            .alloc(Layout::from_size_align_unchecked(size, size));
+        if block.is_null() {
+            self.fallback.alloc(layout)
+        } else {
+            block
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        match size_class(layout) {
+            Some(class) if self.arena.contains(ptr) => self.free_lists[class].push(ptr),
+            _ => self.fallback.dealloc(ptr, layout),
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        if self.arena.contains(ptr) && size_class(layout) == size_class(new_layout) {
+            return ptr;
+        }
+        realloc_by_copy(self, ptr, layout, new_size)
+    }
+}
+
+/// The index of the size class for `layout`, `None` when it's larger than
+/// [`MAX_CLASS`]
+///
+/// Blocks are aligned to their size, so the alignment is included in the
+/// size.
+fn size_class(layout: Layout) -> Option<usize> {
+    let size = layout
+        .size()
+        .max(layout.align())
+        .max(MIN_CLASS)
+        .checked_next_power_of_two()?;
+    (size <= MAX_CLASS).then(|| (size.trailing_zeros() - MIN_CLASS.trailing_zeros()) as usize)
+}
+
+/// The size of the blocks in size class `class`
+fn class_size(class: usize) -> usize {
+    MIN_CLASS << class
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backend::test::Null;
+    use yare::parameterized;
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).expect("Layout should be valid")
+    }
+
+    #[parameterized(
+    byte = {1, 1, Some(16)},
+    min_class = {16, 8, Some(16)},
+    rounded_up = {17, 1, Some(32)},
+    alignment = {8, 256, Some(256)},
+    max_class = {4096, 8, Some(4096)},
+    too_large = {4097, 8, None},
+    too_aligned = {8, 8192, None},
+    )]
+    fn size_class_of_layout(size: usize, align: usize, expected: Option<usize>) {
+        let class = size_class(layout(size, align));
+
+        assert_eq!(class.map(class_size), expected);
+    }
+
+    #[parameterized(
+    small = {10, 1},
+    over_aligned = {100, 128},
+    max_class = {4096, 16},
+    )]
+    fn freed_block_is_reused(size: usize, align: usize) {
+        let slab = Slab::<{ 16 << 10 }, Null>::INIT;
+        let layout = layout(size, align);
+
+        unsafe {
+            let first = slab.alloc(layout);
+            assert!(!first.is_null());
+            assert_eq!(first as usize % align, 0);
+
+            slab.dealloc(first, layout);
+            let second = slab.alloc(layout);
+
+            assert_eq!(first, second);
+        }
+    }
+
+    #[test]
+    fn size_classes_are_separate() {
+        let slab = Slab::<1024, Null>::INIT;
+
+        unsafe {
+            let small = slab.alloc(layout(16, 8));
+            slab.dealloc(small, layout(16, 8));
+            let large = slab.alloc(layout(32, 8));
+
+            assert!(!large.is_null());
+            assert_ne!(small, large);
+        }
+    }
+
+    #[test]
+    fn exhausted_region_uses_fallback() {
+        let slab = Slab::<64, Null>::INIT;
+
+        unsafe {
+            assert!(!slab.alloc(layout(64, 1)).is_null());
+            // `Null` is always out of memory
+            assert!(slab.alloc(layout(64, 1)).is_null());
+            assert!(slab.alloc(layout(MAX_CLASS + 1, 1)).is_null());
+        }
+    }
+
+    #[test]
+    fn realloc_within_size_class_is_in_place() {
+        let slab = Slab::<1024, Null>::INIT;
+
+        unsafe {
+            let ptr = slab.alloc(layout(20, 4));
+            core::ptr::copy_nonoverlapping(b"abcd".as_ptr(), ptr, 4);
+
+            assert_eq!(slab.realloc(ptr, layout(20, 4), 32), ptr);
+
+            let moved = slab.realloc(ptr, layout(32, 4), 100);
+            assert!(!moved.is_null());
+            assert_ne!(moved, ptr);
+            assert_eq!(core::slice::from_raw_parts(moved, 4), b"abcd");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! A lock which doesn't allocate, for guarding the allocators' bookkeeping
+
+use core::hint;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A spin lock
+///
+/// The SGX SDK's mutexes may allocate, so they can't be used from within an
+/// allocator. The critical sections of the allocators are short, so spinning
+/// is acceptable.
+#[derive(Debug)]
+pub(crate) struct SpinLock {
+    locked: AtomicBool,
+}
+
+impl SpinLock {
+    /// Create a new unlocked [`SpinLock`]
+    pub(crate) const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// Acquire the lock, spinning until it's available
+    ///
+    /// The lock is released when the returned [`SpinGuard`] is dropped.
+    pub(crate) fn lock(&self) -> SpinGuard {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        SpinGuard { lock: self }
+    }
+}
+
+/// Releases a [`SpinLock`] when dropped
+#[derive(Debug)]
+pub(crate) struct SpinGuard<'a> {
+    lock: &'a SpinLock,
+}
+
+impl Drop for SpinGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
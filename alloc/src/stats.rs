@@ -0,0 +1,131 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Accounting of the heap usage of the [`Allocator`](crate::Allocator)
+//!
+//! The heap usage is only tracked with the `stats` feature, otherwise
+//! recording an allocation or deallocation does nothing.
+//!
+//! Relaxed ordering is used for all of the counters as they are only
+//! informational, they don't guard any other memory. A [`Stats`] snapshot may
+//! observe an allocation in one counter and not yet in another.
+
+#[cfg(feature = "stats")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The bytes currently allocated
+#[cfg(feature = "stats")]
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The most bytes that have been allocated at one time
+#[cfg(feature = "stats")]
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of allocations that have been made
+#[cfg(feature = "stats")]
+static ALLOCATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The size of the largest single allocation that has been made
+#[cfg(feature = "stats")]
+static LARGEST_ALLOCATION: AtomicUsize = AtomicUsize::new(0);
+
+/// A snapshot of the heap usage of the [`Allocator`](crate::Allocator)
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Stats {
+    /// The bytes currently allocated
+    pub live_bytes: usize,
+    /// The most bytes that have been allocated at one time
+    pub peak_bytes: usize,
+    /// The number of allocations that have been made, including those which
+    /// have since been freed
+    pub allocation_count: usize,
+    /// The size, in bytes, of the largest single allocation that has been
+    /// made
+    pub largest_allocation: usize,
+}
+
+/// A snapshot of the heap usage of the [`Allocator`](crate::Allocator)
+///
+/// Only allocations made through the [`Allocator`](crate::Allocator) are
+/// counted, memory allocated directly from the SGX SDK, for instance by C
+/// code in the enclave, isn't included.
+///
+/// # Examples
+///
+/// ```
+/// let stats = mc_sgx_alloc::stats();
+/// assert!(stats.live_bytes <= stats.peak_bytes);
+/// ```
+#[cfg(feature = "stats")]
+pub fn stats() -> Stats {
+    Stats {
+        live_bytes: live_bytes(),
+        peak_bytes: peak_bytes(),
+        allocation_count: ALLOCATION_COUNT.load(Ordering::Relaxed),
+        largest_allocation: LARGEST_ALLOCATION.load(Ordering::Relaxed),
+    }
+}
+
+/// Record an allocation of `size` bytes
+#[cfg(feature = "stats")]
+pub(crate) fn record_alloc(size: usize) {
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+    ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+    LARGEST_ALLOCATION.fetch_max(size, Ordering::Relaxed);
+}
+
+/// Record an allocation of `size` bytes, nothing is tracked without the
+/// `stats` feature
+#[cfg(not(feature = "stats"))]
+pub(crate) fn record_alloc(_size: usize) {}
+
+/// Record a deallocation of `size` bytes
+#[cfg(feature = "stats")]
+pub(crate) fn record_dealloc(size: usize) {
+    LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+
+/// Record a deallocation of `size` bytes, nothing is tracked without the
+/// `stats` feature
+#[cfg(not(feature = "stats"))]
+pub(crate) fn record_dealloc(_size: usize) {}
+
+/// The bytes currently allocated
+#[cfg(feature = "stats")]
+pub(crate) fn live_bytes() -> usize {
+    LIVE_BYTES.load(Ordering::Relaxed)
+}
+
+/// The most bytes that have been allocated at one time
+#[cfg(feature = "stats")]
+pub(crate) fn peak_bytes() -> usize {
+    PEAK_BYTES.load(Ordering::Relaxed)
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod test {
+    use super::*;
+
+    // The counters are global, so a single test exercises them to avoid
+    // interference between tests.
+    #[test]
+    fn heap_usage_is_tracked() {
+        let (live, peak) = (live_bytes(), peak_bytes());
+
+        record_alloc(100);
+        record_alloc(50);
+        record_dealloc(100);
+
+        assert_eq!(live_bytes(), live + 50);
+        assert_eq!(peak_bytes(), peak.max(live + 150));
+
+        record_dealloc(50);
+
+        assert_eq!(live_bytes(), live);
+
+        let stats = stats();
+        assert!(stats.allocation_count >= 2);
+        assert!(stats.largest_allocation >= 100);
+    }
+}
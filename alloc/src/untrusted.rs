@@ -0,0 +1,346 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Allocations in the host's memory, outside of the enclave
+//!
+//! The memory comes from the host, via `ocall_untrusted_alloc()` and
+//! `ocall_untrusted_free()`, see `mc-sgx-alloc-untrusted`. The SGX SDK's
+//! `sgx_ocalloc()` isn't used as its memory is on the untrusted stack, and
+//! only lives until the next ocall returns.
+//!
+//! The host can read and write the memory at any time. So the contents are
+//! never referenced from the enclave, they're only copied in or out, one
+//! volatile access per value, and only for [`Plain`] types.
+
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::ffi::{c_int, c_void};
+use core::mem;
+use core::ptr::{self, NonNull};
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_core_types::Error;
+use mc_sgx_util::ResultInto;
+
+/// Types which can be copied to and from the host's memory
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type, as the host can write
+/// anything to its memory. The type must have no padding, as copying padding
+/// to the host could leak the enclave's memory.
+pub unsafe trait Plain: Copy {}
+
+macro_rules! plain {
+    ($($ty:ty),*) => {
+        $(unsafe impl Plain for $ty {})*
+    };
+}
+
+plain!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+/// Allocates memory in the host's memory
+///
+/// Every allocation is checked to be entirely outside of the enclave, so the
+/// host can't trick the enclave into handing out its own memory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UntrustedAllocator;
+
+impl UntrustedAllocator {
+    /// Allocate host memory as described by `layout`
+    ///
+    /// Zero sized layouts don't allocate, they get a dangling pointer.
+    ///
+    /// # Errors
+    /// * `Error::OutOfMemory` if the host couldn't allocate the memory.
+    /// * `Error::Unexpected` if the host gave memory which overlaps the
+    ///   enclave.
+    /// * The error of the ocall if the ocall failed.
+    pub fn allocate(&self, layout: Layout) -> Result<NonNull<u8>, Error> {
+        if layout.size() == 0 {
+            return Ok(dangling(layout));
+        }
+
+        let mut memory = ptr::null_mut();
+        unsafe { ocall_untrusted_alloc(&mut memory, layout.size(), layout.align()) }
+            .into_result()?;
+        let memory = NonNull::new(memory as *mut u8).ok_or(Error::OutOfMemory)?;
+        // A host which hands out memory of the enclave could have the enclave
+        // overwrite or disclose its own memory, so the memory is abandoned
+        // rather than freed.
+        if !is_outside_enclave(memory.as_ptr(), layout.size())
+            || memory.as_ptr() as usize % layout.align() != 0
+        {
+            return Err(Error::Unexpected);
+        }
+        Ok(memory)
+    }
+
+    /// Free the host memory at `ptr`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by [`UntrustedAllocator::allocate()`]
+    /// with `layout`.
+    pub unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            // Ignore the result, there's nothing to do if the host fails to
+            // free its own memory
+            let _ =
+                ocall_untrusted_free(ptr.as_ptr() as *mut c_void, layout.size(), layout.align());
+        }
+    }
+}
+
+/// A `T` in the host's memory
+///
+/// The value can only be copied in or out, it's never referenced, as the host
+/// can change it at any time.
+#[derive(Debug)]
+pub struct UntrustedBox<T: Plain> {
+    ptr: NonNull<T>,
+}
+
+impl<T: Plain> UntrustedBox<T> {
+    /// Copy `value` into newly allocated host memory
+    ///
+    /// # Errors
+    /// See [`UntrustedAllocator::allocate()`]
+    pub fn new(value: T) -> Result<Self, Error> {
+        let ptr = UntrustedAllocator.allocate(Layout::new::<T>())?.cast();
+        let mut boxed = Self { ptr };
+        boxed.copy_in(value);
+        Ok(boxed)
+    }
+
+    /// Copy `value` into the host memory
+    pub fn copy_in(&mut self, value: T) {
+        // SAFETY: `ptr` is valid and aligned for a `T` outside of the enclave
+        unsafe { ptr::write_volatile(self.ptr.as_ptr(), value) }
+    }
+
+    /// Copy the current value out of the host memory
+    pub fn copy_out(&self) -> T {
+        // SAFETY: `ptr` is valid and aligned for a `T` outside of the enclave,
+        // and any bit pattern is a valid `T`
+        unsafe { ptr::read_volatile(self.ptr.as_ptr()) }
+    }
+
+    /// The address of the host memory, for passing to an ocall
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// The mutable address of the host memory, for passing to an ocall
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+}
+
+impl<T: Plain> Drop for UntrustedBox<T> {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` was allocated with the layout of `T`
+        unsafe { UntrustedAllocator.deallocate(self.ptr.cast(), Layout::new::<T>()) }
+    }
+}
+
+/// A growable buffer of `T`s in the host's memory, like staging buffers for
+/// ocalls
+///
+/// The elements can only be copied in or out, they're never referenced, as
+/// the host can change them at any time.
+#[derive(Debug)]
+pub struct UntrustedVec<T: Plain = u8> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+}
+
+impl<T: Plain> UntrustedVec<T> {
+    /// Create an empty [`UntrustedVec`], no host memory is allocated
+    pub const fn new() -> Self {
+        Self {
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+
+    /// Create an empty [`UntrustedVec`] with host memory for `capacity`
+    /// elements
+    ///
+    /// # Errors
+    /// `Error::OutOfMemory` if the size of `capacity` elements overflows,
+    /// otherwise see [`UntrustedAllocator::allocate()`]
+    pub fn with_capacity(capacity: usize) -> Result<Self, Error> {
+        let ptr = UntrustedAllocator.allocate(array_layout::<T>(capacity)?)?;
+        Ok(Self {
+            ptr: ptr.cast(),
+            len: 0,
+            capacity,
+        })
+    }
+
+    /// Copy `elements` into the host memory, after the current elements
+    ///
+    /// Grows the host memory as needed.
+    ///
+    /// # Errors
+    /// See [`UntrustedVec::with_capacity()`]
+    pub fn copy_in(&mut self, elements: &[T]) -> Result<(), Error> {
+        let len = self
+            .len
+            .checked_add(elements.len())
+            .ok_or(Error::OutOfMemory)?;
+        if len > self.capacity {
+            self.grow(len.max(self.capacity.saturating_mul(2)))?;
+        }
+        for (index, element) in (self.len..len).zip(elements) {
+            // SAFETY: The host memory has room for `len` elements
+            unsafe { ptr::write_volatile(self.ptr.as_ptr().add(index), *element) };
+        }
+        self.len = len;
+        Ok(())
+    }
+
+    /// Copy the elements out of the host memory, into the enclave
+    ///
+    /// The host may be changing the elements while they're copied, so the
+    /// copy is only a snapshot.
+    pub fn copy_out(&self) -> Vec<T> {
+        (0..self.len)
+            // SAFETY: The host memory holds `len` elements, where any bit
+            // pattern is a valid `T`
+            .map(|index| unsafe { ptr::read_volatile(self.ptr.as_ptr().add(index)) })
+            .collect()
+    }
+
+    /// Set the number of elements, for instance after an ocall has written
+    /// into the host memory
+    ///
+    /// # Panics
+    /// If `len` exceeds the capacity.
+    pub fn set_len(&mut self, len: usize) {
+        assert!(
+            len <= self.capacity,
+            "length {len} exceeds the capacity {}",
+            self.capacity
+        );
+        self.len = len;
+    }
+
+    /// Remove all of the elements, keeping the host memory
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// The number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Are there no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of elements there's host memory for
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The address of the host memory, for passing to an ocall
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// The mutable address of the host memory, for passing to an ocall
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Move the elements to new host memory for `capacity` elements
+    fn grow(&mut self, capacity: usize) -> Result<(), Error> {
+        let mut grown = Self::with_capacity(capacity)?;
+        for index in 0..self.len {
+            // SAFETY: Both are host memory with room for `len` elements, where
+            // any bit pattern is a valid `T`
+            unsafe {
+                let element = ptr::read_volatile(self.ptr.as_ptr().add(index));
+                ptr::write_volatile(grown.ptr.as_ptr().add(index), element);
+            }
+        }
+        grown.len = self.len;
+        mem::swap(self, &mut grown);
+        Ok(())
+    }
+}
+
+impl<T: Plain> Default for UntrustedVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Plain> Drop for UntrustedVec<T> {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` was allocated with the layout of `capacity` elements
+        unsafe {
+            UntrustedAllocator.deallocate(
+                self.ptr.cast(),
+                Layout::array::<T>(self.capacity).expect("Layout was valid when allocated"),
+            )
+        }
+    }
+}
+
+/// The layout of `len` `T`s
+fn array_layout<T>(len: usize) -> Result<Layout, Error> {
+    Layout::array::<T>(len).map_err(|_| Error::OutOfMemory)
+}
+
+/// A non null, aligned, pointer for zero sized allocations
+fn dangling(layout: Layout) -> NonNull<u8> {
+    // SAFETY: Alignments are non zero
+    unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+}
+
+/// Is the range `ptr..ptr + size` entirely outside of the enclave
+fn is_outside_enclave(ptr: *const u8, size: usize) -> bool {
+    // SAFETY: Only inspects the address range, it isn't dereferenced.
+    unsafe { sgx_is_outside_enclave(ptr as *const c_void, size) == 1 }
+}
+
+extern "C" {
+    /// The ocall to allocate host memory
+    ///
+    /// # Arguments
+    /// * `retval` - Output, the allocated memory, null on failure
+    /// * `size` - The byte length of the memory
+    /// * `align` - The alignment of the memory, a power of two
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the ocall was made.
+    fn ocall_untrusted_alloc(retval: *mut *mut c_void, size: usize, align: usize) -> sgx_status_t;
+
+    /// The ocall to free host memory allocated by `ocall_untrusted_alloc()`
+    ///
+    /// # Arguments
+    /// * `ptr` - The memory to free
+    /// * `size` - The byte length `ptr` was allocated with
+    /// * `align` - The alignment `ptr` was allocated with
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the ocall was made.
+    fn ocall_untrusted_free(ptr: *mut c_void, size: usize, align: usize) -> sgx_status_t;
+
+    /// Check whether a buffer is entirely outside of the enclave
+    ///
+    /// # Arguments
+    /// * `addr` - The start of the buffer
+    /// * `size` - The byte length of the buffer
+    ///
+    /// # Returns
+    /// 1 when the buffer is strictly outside of the enclave, 0 otherwise.
+    fn sgx_is_outside_enclave(addr: *const c_void, size: usize) -> c_int;
+}
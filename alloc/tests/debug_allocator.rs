@@ -0,0 +1,49 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Tests for listing the outstanding allocations of the `DebugAllocator`
+//! running in an enclave.
+//!
+//! The debug allocator enclave uses a `DebugAllocator` as its global
+//! allocator. The listing is written to the stderr ocall, which is captured
+//! with `mc_sgx_io_untrusted::stderr_sink()`.
+
+use mc_sgx_io_untrusted::stderr_sink;
+use mc_sgx_urts::EnclaveBuilder;
+use mc_sgx_util::ResultInto;
+use std::sync::Mutex;
+use test_enclave::{ecall_alloc_dump_outstanding, ENCLAVE_DEBUG_ALLOC};
+
+/// The stderr output of the enclave
+static STDERR: Mutex<String> = Mutex::new(String::new());
+
+fn capture_stderr(message: &[u8]) {
+    let message = std::str::from_utf8(message).expect("Stderr should be UTF-8");
+    STDERR
+        .lock()
+        .expect("Mutex has been poisoned")
+        .push_str(message);
+}
+
+#[test]
+fn live_allocation_is_listed() {
+    stderr_sink(&capture_stderr);
+    let enclave = EnclaveBuilder::from(ENCLAVE_DEBUG_ALLOC).create().unwrap();
+
+    unsafe { ecall_alloc_dump_outstanding(*enclave.id(), 64, 100) }
+        .into_result()
+        .expect("Ecall failed");
+
+    let listing = STDERR.lock().expect("Mutex has been poisoned").clone();
+    let lines = listing.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "Unexpected listing:\n{listing}");
+    assert_eq!(lines[0], "outstanding allocations:");
+    let (address, allocation) = lines[1]
+        .trim_start()
+        .split_once(": ")
+        .expect("Allocation should have an address");
+    let address = usize::from_str_radix(address.trim_start_matches("0x"), 16)
+        .expect("Address should be hexadecimal");
+    assert_eq!(address % 64, 0);
+    assert_eq!(allocation, "100 bytes, align 64");
+    assert_eq!(lines[2], "1 outstanding allocations, 100 bytes");
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+#![doc = include_str!("../README.md")]
+#![deny(missing_docs, missing_debug_implementations)]
+
+//! Module for providing host memory to an enclave.
+//!
+//! The enclave's `UntrustedAllocator` allocates from the host's heap via
+//! `ocall_untrusted_alloc()` and frees via `ocall_untrusted_free()`. The
+//! enclave checks that the memory is outside of the enclave, the host only
+//! needs to allocate it.
+
+use std::alloc::{self, Layout};
+use std::ffi::c_void;
+use std::ptr;
+
+#[no_mangle]
+/// The ocall that allocates host memory for the enclave.
+///
+/// # Arguments
+/// * `size` - The byte length of the memory, non zero.
+/// * `align` - The alignment of the memory, a power of two.
+///
+/// # Returns
+/// The allocated memory, null if it couldn't be allocated or the layout is
+/// invalid.
+extern "C" fn ocall_untrusted_alloc(size: usize, align: usize) -> *mut c_void {
+    match Layout::from_size_align(size, align) {
+        // SAFETY: The size is checked to be non zero
+        Ok(layout) if size != 0 => unsafe { alloc::alloc(layout) as *mut c_void },
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+/// The ocall that frees host memory allocated by `ocall_untrusted_alloc()`.
+///
+/// # Arguments
+/// * `ptr` - The memory to free.
+/// * `size` - The byte length `ptr` was allocated with.
+/// * `align` - The alignment `ptr` was allocated with.
+extern "C" fn ocall_untrusted_free(ptr: *mut c_void, size: usize, align: usize) {
+    // SAFETY: Converting from C interface to Rust. We must rely on the enclave
+    // side of the implementation to only free memory it allocated, with the
+    // same layout.
+    unsafe {
+        alloc::dealloc(
+            ptr as *mut u8,
+            Layout::from_size_align_unchecked(size, align),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_sgx_urts::EnclaveBuilder;
+    use mc_sgx_util::ResultInto;
+    use test_enclave::{ecall_untrusted_box_round_trip, ecall_untrusted_vec_round_trip, ENCLAVE};
+    use yare::parameterized;
+
+    #[parameterized(
+    zero_size = {0, 8},
+    zero_align = {8, 0},
+    unaligned = {8, 3},
+    )]
+    fn invalid_layout_is_not_allocated(size: usize, align: usize) {
+        assert!(ocall_untrusted_alloc(size, align).is_null());
+    }
+
+    #[test]
+    fn allocation_is_aligned() {
+        let memory = ocall_untrusted_alloc(100, 64);
+
+        assert!(!memory.is_null());
+        assert_eq!(memory as usize % 64, 0);
+
+        ocall_untrusted_free(memory, 100, 64);
+    }
+
+    #[test]
+    fn box_round_trip() {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        let mut copied = 0;
+        unsafe { ecall_untrusted_box_round_trip(id, 0x0123_4567_89ab_cdef, &mut copied) }
+            .into_result()
+            .unwrap();
+
+        assert_eq!(copied, 0x0123_4567_89ab_cdef);
+    }
+
+    #[parameterized(
+    empty = {0},
+    small = {10},
+    grows = {5000},
+    large = {1 << 19},
+    )]
+    fn vec_round_trip(len: usize) {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        let mut intact = 0;
+        unsafe { ecall_untrusted_vec_round_trip(id, len, &mut intact) }
+            .into_result()
+            .unwrap();
+
+        assert_ne!(intact, 0);
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Provides a [`fmt::Write`] which sends its contents to a sink each time its
+//! buffer fills up.
+
+use crate::WriteBuffer;
+use core::fmt;
+use mc_sgx_core_types::Error;
+
+/// A [`fmt::Write`] which sends its contents to a sink, like
+/// [`stderr_write_all()`](crate::stderr_write_all), each time its
+/// [`WriteBuffer`] fills up.
+///
+/// Unlike [`WriteBuffer`], this can format output of any length without
+/// allocating. The output is split into chunks on UTF-8 character boundaries so
+/// that each chunk is valid UTF-8.
+///
+/// Any remaining contents are sent to the sink with [`flush()`] or when the
+/// [`FlushingWriter`] is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use core::fmt::Write;
+/// use mc_sgx_io::{stderr_write_all, FlushingWriter};
+///
+/// let mut writer = FlushingWriter::new(stderr_write_all);
+/// for i in 0..10_000 {
+///     write!(writer, "{i} ").unwrap();
+/// }
+/// writer.flush().unwrap();
+/// ```
+///
+/// [`flush()`]: FlushingWriter::flush
+pub struct FlushingWriter<F>
+where
+    F: FnMut(&[u8]) -> Result<(), Error>,
+{
+    buffer: WriteBuffer,
+    sink: F,
+    /// The error from `sink`, [`fmt::Error`] can't carry it.
+    error: Option<Error>,
+}
+
+impl<F> FlushingWriter<F>
+where
+    F: FnMut(&[u8]) -> Result<(), Error>,
+{
+    /// Create a new empty [`FlushingWriter`]
+    ///
+    /// # Arguments
+    /// * `sink` - The function to send the contents to when the buffer is
+    ///   full. It is expected to write the entire contents it's given.
+    pub const fn new(sink: F) -> Self {
+        FlushingWriter {
+            buffer: WriteBuffer::new(),
+            sink,
+            error: None,
+        }
+    }
+
+    /// Send any buffered contents to the sink.
+    ///
+    /// # Errors
+    /// When the sink fails, or has previously failed while writing. Once the
+    /// sink fails nothing more will be sent to it, until [`clear()`] is called.
+    ///
+    /// [`clear()`]: FlushingWriter::clear
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let contents: &[u8] = self.buffer.as_ref();
+        if !contents.is_empty() {
+            if let Err(error) = (self.sink)(contents) {
+                self.error = Some(error);
+                return Err(error);
+            }
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// Discard any buffered contents and forget any previous failure of the
+    /// sink, so that the [`FlushingWriter`] can be reused.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.error = None;
+    }
+
+    /// The error from the sink, if it has failed.
+    ///
+    /// [`fmt::Error`] can't carry the reason the sink failed. When a write
+    /// fails this can be used to tell if it was due to the sink or due to a
+    /// formatting trait implementation.
+    pub fn sink_error(&self) -> Option<Error> {
+        self.error
+    }
+}
+
+impl<F> fmt::Write for FlushingWriter<F>
+where
+    F: FnMut(&[u8]) -> Result<(), Error>,
+{
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        let mut rest = string;
+        while !rest.is_empty() {
+            // Only split on character boundaries so that each chunk is valid
+            // UTF-8
+            let mut end = rest.len().min(self.buffer.remaining());
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            if end == 0 {
+                self.flush().map_err(|_| fmt::Error)?;
+                continue;
+            }
+
+            let (chunk, tail) = rest.split_at(end);
+            self.buffer.write_str(chunk)?;
+            rest = tail;
+        }
+        Ok(())
+    }
+}
+
+impl<F> Drop for FlushingWriter<F>
+where
+    F: FnMut(&[u8]) -> Result<(), Error>,
+{
+    fn drop(&mut self) {
+        // Ignore the result, there is no one to report the failure to
+        let _ = self.flush();
+    }
+}
+
+impl<F> fmt::Debug for FlushingWriter<F>
+where
+    F: FnMut(&[u8]) -> Result<(), Error>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlushingWriter")
+            .field("buffer", &self.buffer)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use crate::write_buffer::BUFFER_SIZE;
+    use core::fmt::Write;
+    use std::string::String;
+    use std::vec::Vec;
+    use yare::parameterized;
+
+    fn chunks_of(message: &str) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut writer = FlushingWriter::new(|bytes: &[u8]| {
+            chunks.push(String::from_utf8(bytes.to_vec()).expect("Chunk should be valid UTF-8"));
+            Ok(())
+        });
+        writer
+            .write_str(message)
+            .expect("Shouldn't fail to write message");
+        writer.flush().expect("Shouldn't fail to flush");
+        drop(writer);
+        chunks
+    }
+
+    #[test]
+    fn empty_message_sends_nothing() {
+        assert!(chunks_of("").is_empty());
+    }
+
+    #[parameterized(
+    one_byte = {1},
+    almost_full = {BUFFER_SIZE - 1},
+    full = {BUFFER_SIZE},
+    )]
+    fn message_fitting_in_buffer_is_one_chunk(size: usize) {
+        let message = "a".repeat(size);
+
+        assert_eq!(chunks_of(&message), [message]);
+    }
+
+    #[parameterized(
+    one_over = {BUFFER_SIZE + 1, 2},
+    two_full = {BUFFER_SIZE * 2, 2},
+    two_and_a_bit = {BUFFER_SIZE * 2 + 5, 3},
+    )]
+    fn long_message_is_chunked(size: usize, expected_chunks: usize) {
+        let message = "b".repeat(size);
+
+        let chunks = chunks_of(&message);
+
+        assert_eq!(chunks.len(), expected_chunks);
+        assert!(chunks[..expected_chunks - 1]
+            .iter()
+            .all(|chunk| chunk.len() == BUFFER_SIZE));
+        assert_eq!(chunks.concat(), message);
+    }
+
+    #[test]
+    fn chunks_split_on_character_boundaries() {
+        // 3 bytes per character, so `BUFFER_SIZE` isn't a character boundary
+        let message = "€".repeat(BUFFER_SIZE / 3 + 1);
+
+        let chunks = chunks_of(&message);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), BUFFER_SIZE - BUFFER_SIZE % 3);
+        assert_eq!(chunks.concat(), message);
+    }
+
+    #[test]
+    fn many_small_writes_are_chunked() {
+        let mut chunks = 0;
+        let mut bytes = 0;
+        let mut writer = FlushingWriter::new(|chunk: &[u8]| {
+            chunks += 1;
+            bytes += chunk.len();
+            Ok(())
+        });
+        for i in 0..BUFFER_SIZE {
+            write!(writer, "{}", i % 10).expect("Shouldn't fail to write");
+        }
+        write!(writer, "more").expect("Shouldn't fail to write");
+        drop(writer);
+
+        assert_eq!(chunks, 2);
+        assert_eq!(bytes, BUFFER_SIZE + 4);
+    }
+
+    #[test]
+    fn remaining_contents_are_sent_on_drop() {
+        let mut chunks = Vec::new();
+        let mut writer = FlushingWriter::new(|bytes: &[u8]| {
+            chunks.push(bytes.to_vec());
+            Ok(())
+        });
+        writer.write_str("what").expect("Shouldn't fail to write");
+        drop(writer);
+
+        assert_eq!(chunks, [b"what"]);
+    }
+
+    #[test]
+    fn failed_sink_stops_writing() {
+        let message = "c".repeat(BUFFER_SIZE * 3);
+        let mut calls = 0;
+        let mut writer = FlushingWriter::new(|_: &[u8]| {
+            calls += 1;
+            Err(Error::FileBadStatus)
+        });
+
+        assert!(writer.write_str(&message).is_err());
+        assert_eq!(writer.sink_error(), Some(Error::FileBadStatus));
+        assert_eq!(writer.flush(), Err(Error::FileBadStatus));
+        drop(writer);
+
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn clear_recovers_from_failed_sink() {
+        let message = "d".repeat(BUFFER_SIZE + 1);
+        let mut calls = 0;
+        let mut chunks = Vec::new();
+        let mut writer = FlushingWriter::new(|bytes: &[u8]| {
+            calls += 1;
+            if calls == 1 {
+                return Err(Error::FileBadStatus);
+            }
+            chunks.push(bytes.to_vec());
+            Ok(())
+        });
+        assert!(writer.write_str(&message).is_err());
+
+        writer.clear();
+
+        assert_eq!(writer.sink_error(), None);
+        writer
+            .write_str(&message)
+            .expect("Shouldn't fail to write message");
+        writer.flush().expect("Shouldn't fail to flush");
+        drop(writer);
+
+        assert_eq!(calls, 3);
+        assert_eq!(chunks.concat(), message.as_bytes());
+    }
+}
@@ -4,13 +4,20 @@
 #![deny(missing_docs, missing_debug_implementations)]
 #![no_std]
 
+mod flushing_writer;
+#[cfg(feature = "log")]
+pub mod logger;
+mod print;
 mod write_buffer;
 
 use core::ffi::c_void;
+pub use flushing_writer::FlushingWriter;
 use mc_sgx_core_sys_types::sgx_status_t;
 use mc_sgx_core_types::Error;
 use mc_sgx_util::ResultInto;
-pub use write_buffer::WriteBuffer;
+#[doc(hidden)]
+pub use print::{_eprint, _print};
+pub use write_buffer::{WriteBuffer, BUFFER_SIZE, TRUNCATION_MARKER};
 
 /// Write the entire `buffer` into the hosts stderr sink.
 ///
@@ -26,6 +33,20 @@ pub fn stderr_write_all(buffer: &[u8]) -> Result<(), Error> {
     unsafe { ocall_stderr(buffer.as_ptr() as *const c_void, buffer.len()) }.into_result()
 }
 
+/// Write the entire `buffer` into the hosts stdout sink.
+///
+/// # Arguments
+/// * `buffer` - The buffer to write.
+///
+/// # Errors
+/// When not all of `buffer` could be written to the hosts stdout sink.
+///
+/// If there is an error, no assumptions should be made about the amount of
+/// `buffer` that was written.
+pub fn stdout_write_all(buffer: &[u8]) -> Result<(), Error> {
+    unsafe { ocall_stdout(buffer.as_ptr() as *const c_void, buffer.len()) }.into_result()
+}
+
 extern "C" {
     /// The ocall to send stderr messages to
     ///
@@ -39,6 +60,19 @@ extern "C" {
     /// An error status if not all of the data could be written to the sink. No
     /// assumptions are made about how much data was written on error.
     fn ocall_stderr(input: *const c_void, len: usize) -> sgx_status_t;
+
+    /// The ocall to send stdout messages to
+    ///
+    /// # Arguments
+    /// * `input` - The input buffer/stream. Should be u8/bytes
+    /// * `len` - The byte length of `input`
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when all of input was successfully written
+    /// to the untrusted stdout sink.
+    /// An error status if not all of the data could be written to the sink. No
+    /// assumptions are made about how much data was written on error.
+    fn ocall_stdout(input: *const c_void, len: usize) -> sgx_status_t;
 }
 
 // Done out here so that `serial_test` works, since it uses "::std" in the macro
@@ -55,12 +89,15 @@ mod tests {
     use std::sync::Mutex;
 
     static TEST_STREAM: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+    static TEST_STDOUT_STREAM: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
     static TEST_STREAM_RESULT: Lazy<Mutex<sgx_status_t>> =
         Lazy::new(|| Mutex::new(sgx_status_t::SGX_SUCCESS));
 
     fn reset_test_stream() {
         let mut stream = TEST_STREAM.lock().expect("Mutex has been poisoned");
         stream.clear();
+        let mut stream = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        stream.clear();
         let mut status = TEST_STREAM_RESULT.lock().expect("Mutex has been poisoned");
         *status = sgx_status_t::SGX_SUCCESS;
     }
@@ -77,6 +114,18 @@ mod tests {
         *status
     }
 
+    #[no_mangle]
+    extern "C" fn ocall_stdout(input: *const c_void, len: usize) -> sgx_status_t {
+        let bytes = unsafe { slice::from_raw_parts(input as *const u8, len) };
+        let message =
+            std::str::from_utf8(bytes).expect("Expected valid UTF8 from stdout in enclave");
+        let mut stream = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        stream.clear();
+        stream.push_str(message);
+        let status = TEST_STREAM_RESULT.lock().expect("Mutex has been poisoned");
+        *status
+    }
+
     #[test]
     #[serial]
     fn single_line_output_to_stderr() {
@@ -112,4 +161,108 @@ mod tests {
         let error = stderr_write_all(b"what").unwrap_err();
         assert_eq!(error, Error::FileBadStatus);
     }
+
+    #[test]
+    #[serial]
+    fn single_line_output_to_stdout() {
+        reset_test_stream();
+        let test_message = b"what";
+        stdout_write_all(test_message).expect("Expected the write to succeed");
+
+        let written = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_bytes(), test_message);
+        let stderr = TEST_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(stderr.as_str(), "");
+    }
+
+    #[test]
+    #[serial]
+    fn multi_line_output_to_stdout() {
+        reset_test_stream();
+        let test_message = b"this\nhas\nmultiple\nlines";
+        stdout_write_all(test_message).expect("Expected the write to succeed");
+
+        let written = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_bytes(), test_message);
+    }
+
+    #[test]
+    #[serial]
+    fn error_when_outputting_to_stdout() {
+        reset_test_stream();
+        let expected_error = sgx_status_t::SGX_ERROR_FILE_BAD_STATUS;
+        {
+            let mut status = TEST_STREAM_RESULT.lock().expect("Mutex has been poisoned");
+            *status = expected_error;
+        }
+
+        let error = stdout_write_all(b"what").unwrap_err();
+        assert_eq!(error, Error::FileBadStatus);
+    }
+
+    #[test]
+    #[serial]
+    fn println_to_stdout() {
+        reset_test_stream();
+        println!("{} + {} = {}", 1, 2, 1 + 2);
+
+        let written = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_str(), "1 + 2 = 3\n");
+    }
+
+    #[test]
+    #[serial]
+    fn print_to_stdout() {
+        reset_test_stream();
+        let name = "enclave";
+        print!("hello {name}");
+
+        let written = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_str(), "hello enclave");
+    }
+
+    #[test]
+    #[serial]
+    fn eprintln_to_stderr() {
+        reset_test_stream();
+        eprintln!("{} + {} = {}", 1, 2, 1 + 2);
+
+        let written = TEST_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_str(), "1 + 2 = 3\n");
+        let stdout = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(stdout.as_str(), "");
+    }
+
+    #[test]
+    #[serial]
+    fn eprint_to_stderr() {
+        reset_test_stream();
+        eprint!("what");
+
+        let written = TEST_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_str(), "what");
+    }
+
+    #[test]
+    #[serial]
+    fn empty_println_is_a_newline() {
+        reset_test_stream();
+        println!();
+
+        let written = TEST_STDOUT_STREAM.lock().expect("Mutex has been poisoned");
+        assert_eq!(written.as_str(), "\n");
+    }
+
+    #[test]
+    #[serial]
+    #[should_panic(expected = "failed printing to stdout")]
+    fn println_panics_on_error() {
+        reset_test_stream();
+        {
+            let mut status = TEST_STREAM_RESULT.lock().expect("Mutex has been poisoned");
+            *status = sgx_status_t::SGX_ERROR_FILE_BAD_STATUS;
+        }
+
+        println!("what");
+    }
 }
@@ -0,0 +1,278 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! [`log`] backend which sends the records to the host.
+//!
+//! [`Logger`] sends the level, target, module path and message of the records
+//! to the host as separate fields of the `ocall_log()` ocall, see
+//! [`log_write()`]. The host doesn't need to parse anything to keep them, see
+//! `mc-sgx-io-untrusted`.
+
+use crate::WriteBuffer;
+use core::ffi::c_char;
+use core::fmt::Write;
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_core_types::Error;
+use mc_sgx_util::ResultInto;
+
+/// A [`Log`] implementation which sends the records to the host with the
+/// `ocall_log()` ocall.
+///
+/// Records are filtered by [`log::max_level()`], which [`init()`] sets.
+#[derive(Debug, Default)]
+pub struct Logger;
+
+/// The logger registered with [`log::set_logger()`] by [`init()`]
+static LOGGER: Logger = Logger;
+
+/// Use the [`Logger`] for the [`log`] macros.
+///
+/// # Arguments
+/// * `max_level` - The most verbose level that will be sent to the host.
+///   Records of a more verbose level are discarded inside of the enclave.
+///
+/// # Errors
+/// If a logger has already been set.
+pub fn init(max_level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        write_record(record);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Send `record` to the host with [`log_write()`]
+///
+/// Messages which don't fit in the buffer are truncated.
+fn write_record(record: &Record) {
+    // The buffer is on the stack instead of a static behind a mutex so
+    // that records can be logged while formatting another record.
+    let mut buffer = WriteBuffer::new_lossy();
+    let message = match write!(buffer, "{}", record.args()) {
+        Ok(()) => buffer.as_ref(),
+        _ => "Failed to format log record.",
+    };
+
+    // Ignore the result, there isn't anywhere else to report the failure
+    let _ = log_write(
+        record.level(),
+        record.target(),
+        record.module_path(),
+        message,
+    );
+}
+
+/// Write a log message into the hosts log sink.
+///
+/// # Arguments
+/// * `level` - The level of the message.
+/// * `target` - The target of the message, usually the module path.
+/// * `module_path` - The module path the message came from, if known.
+/// * `message` - The message.
+///
+/// # Errors
+/// When the host failed to receive the message.
+pub fn log_write(
+    level: Level,
+    target: &str,
+    module_path: Option<&str>,
+    message: &str,
+) -> Result<(), Error> {
+    // An empty module path is the same as none, module paths are never empty
+    let module_path = module_path.unwrap_or_default();
+    unsafe {
+        ocall_log(
+            level as u32,
+            target.as_ptr() as *const c_char,
+            target.len(),
+            module_path.as_ptr() as *const c_char,
+            module_path.len(),
+            message.as_ptr() as *const c_char,
+            message.len(),
+        )
+    }
+    .into_result()
+}
+
+extern "C" {
+    /// The ocall to send log messages to
+    ///
+    /// # Arguments
+    /// * `level` - The [`Level`] of the message, 1 for [`Level::Error`]
+    ///   through 5 for [`Level::Trace`].
+    /// * `target` - The target of the message. Should be UTF-8.
+    /// * `target_len` - The byte length of `target`
+    /// * `module_path` - The module path of the message, empty when there is
+    ///   none. Should be UTF-8.
+    /// * `module_path_len` - The byte length of `module_path`
+    /// * `msg` - The message. Should be UTF-8.
+    /// * `msg_len` - The byte length of `msg`
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the message was successfully given to
+    /// the untrusted log sink.
+    fn ocall_log(
+        level: u32,
+        target: *const c_char,
+        target_len: usize,
+        module_path: *const c_char,
+        module_path_len: usize,
+        msg: *const c_char,
+        msg_len: usize,
+    ) -> sgx_status_t;
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use core::slice;
+    use serial_test::serial;
+    use std::string::String;
+    use std::sync::Mutex;
+
+    /// The level, target, module path and message of the last `ocall_log()`
+    type LogFields = (u32, String, String, String);
+    static LAST_LOG: Mutex<Option<LogFields>> = Mutex::new(None);
+
+    fn utf8(ptr: *const c_char, len: usize) -> String {
+        let bytes = unsafe { slice::from_raw_parts(ptr as *const u8, len) };
+        String::from_utf8(bytes.to_vec()).expect("Expected valid UTF8")
+    }
+
+    #[no_mangle]
+    extern "C" fn ocall_log(
+        level: u32,
+        target: *const c_char,
+        target_len: usize,
+        module_path: *const c_char,
+        module_path_len: usize,
+        msg: *const c_char,
+        msg_len: usize,
+    ) -> sgx_status_t {
+        let mut last = LAST_LOG.lock().expect("Mutex has been poisoned");
+        *last = Some((
+            level,
+            utf8(target, target_len),
+            utf8(module_path, module_path_len),
+            utf8(msg, msg_len),
+        ));
+        sgx_status_t::SGX_SUCCESS
+    }
+
+    #[test]
+    #[serial]
+    fn log_write_sends_fields() {
+        for (level, expected_level) in [
+            (Level::Error, 1),
+            (Level::Warn, 2),
+            (Level::Info, 3),
+            (Level::Debug, 4),
+            (Level::Trace, 5),
+        ] {
+            log_write(level, "a::target", Some("a::module"), "a message")
+                .expect("Expected the write to succeed");
+
+            let last = LAST_LOG.lock().expect("Mutex has been poisoned");
+            assert_eq!(
+                *last,
+                Some((
+                    expected_level,
+                    "a::target".into(),
+                    "a::module".into(),
+                    "a message".into()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn log_write_without_module_path_sends_empty_module_path() {
+        log_write(Level::Info, "a::target", None, "a message")
+            .expect("Expected the write to succeed");
+
+        let last = LAST_LOG.lock().expect("Mutex has been poisoned");
+        assert_eq!(
+            *last,
+            Some((3, "a::target".into(), "".into(), "a message".into()))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn record_formats_message() {
+        write_record(
+            &Record::builder()
+                .level(Level::Info)
+                .target("enclave")
+                .module_path(Some("enclave::module"))
+                .args(format_args!("{} + {} = {}", 1, 2, 1 + 2))
+                .build(),
+        );
+
+        let last = LAST_LOG.lock().expect("Mutex has been poisoned");
+        assert_eq!(
+            *last,
+            Some((
+                3,
+                "enclave".into(),
+                "enclave::module".into(),
+                "1 + 2 = 3".into()
+            ))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn multi_line_record() {
+        write_record(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("enclave")
+                .args(format_args!("this\nhas\nlines"))
+                .build(),
+        );
+
+        let last = LAST_LOG.lock().expect("Mutex has been poisoned");
+        assert_eq!(
+            *last,
+            Some((2, "enclave".into(), "".into(), "this\nhas\nlines".into()))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn record_too_big_for_buffer_is_truncated() {
+        let message = "a".repeat(crate::write_buffer::BUFFER_SIZE + 1);
+        write_record(
+            &Record::builder()
+                .level(Level::Error)
+                .target("enclave")
+                .args(format_args!("{message}"))
+                .build(),
+        );
+
+        let last = LAST_LOG.lock().expect("Mutex has been poisoned");
+        let (level, target, _, logged) = last.as_ref().expect("Record should have been logged");
+        assert_eq!((*level, target.as_str()), (1, "enclave"));
+        assert_eq!(logged.len(), crate::write_buffer::BUFFER_SIZE);
+        assert!(logged.ends_with(crate::TRUNCATION_MARKER));
+        assert!(message.starts_with(logged.trim_end_matches(crate::TRUNCATION_MARKER)));
+    }
+}
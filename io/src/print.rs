@@ -0,0 +1,239 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Support for the [`print!`](crate::print), [`println!`](crate::println),
+//! [`eprint!`](crate::eprint) and [`eprintln!`](crate::eprintln) macros.
+//!
+//! The messages are formatted with a [`FlushingWriter`] on the stack. This
+//! allows for messages of any length without allocating.
+
+use crate::{stderr_write_all, stdout_write_all, FlushingWriter};
+use core::fmt::{self, Write};
+use mc_sgx_core_types::Error;
+
+/// Prints to the hosts stdout sink.
+///
+/// Equivalent to the [`println!`] macro except that a newline is not printed at
+/// the end of the message.
+///
+/// Each call is sent to the host with as few ocalls as possible, messages which
+/// fit in a [`WriteBuffer`](crate::WriteBuffer) are sent with one ocall.
+/// Longer messages are sent in multiple chunks.
+///
+/// Use `print!` only for the primary output of your program. Use [`eprint!`]
+/// instead to print error and progress messages.
+///
+/// # Panics
+///
+/// Panics if writing to the hosts stdout sink fails.
+///
+/// # Examples
+///
+/// ```
+/// use mc_sgx_io::print;
+///
+/// print!("this ");
+/// print!("will ");
+/// print!("be ");
+/// print!("on ");
+/// print!("the ");
+/// print!("same ");
+/// print!("line ");
+/// ```
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {{
+        $crate::_print(format_args!($($arg)*));
+    }};
+}
+
+/// Prints to the hosts stdout sink, with a newline.
+///
+/// This macro uses the same syntax as [`format!`], but writes to the hosts
+/// stdout sink instead.
+///
+/// Use `println!` only for the primary output of your program. Use
+/// [`eprintln!`] instead to print error and progress messages.
+///
+/// # Panics
+///
+/// Panics if writing to the hosts stdout sink fails.
+///
+/// # Examples
+///
+/// ```
+/// use mc_sgx_io::println;
+///
+/// println!(); // prints just a newline
+/// println!("hello there!");
+/// println!("format {} arguments", "some");
+/// let local_variable = "some";
+/// println!("format {local_variable} arguments");
+/// ```
+///
+/// [`format!`]: https://doc.rust-lang.org/std/macro.format.html
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {{
+        $crate::_print(format_args!("{}\n", format_args!($($arg)*)));
+    }};
+}
+
+/// Prints to the hosts stderr sink.
+///
+/// Equivalent to the [`print!`] macro, except that output goes to the hosts
+/// stderr sink instead of stdout.
+///
+/// Use `eprint!` only for error and progress messages. Use [`print!`] instead
+/// for the primary output of your program.
+///
+/// # Panics
+///
+/// Panics if writing to the hosts stderr sink fails.
+///
+/// # Examples
+///
+/// ```
+/// use mc_sgx_io::eprint;
+///
+/// eprint!("Error: Could not complete task");
+/// ```
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => {{
+        $crate::_eprint(format_args!($($arg)*));
+    }};
+}
+
+/// Prints to the hosts stderr sink, with a newline.
+///
+/// Equivalent to the [`println!`] macro, except that output goes to the hosts
+/// stderr sink instead of stdout.
+///
+/// Use `eprintln!` only for error and progress messages. Use [`println!`]
+/// instead for the primary output of your program.
+///
+/// # Panics
+///
+/// Panics if writing to the hosts stderr sink fails.
+///
+/// # Examples
+///
+/// ```
+/// use mc_sgx_io::eprintln;
+///
+/// eprintln!("Error: Could not complete task");
+/// ```
+#[macro_export]
+macro_rules! eprintln {
+    () => {
+        $crate::eprint!("\n")
+    };
+    ($($arg:tt)*) => {{
+        $crate::_eprint(format_args!("{}\n", format_args!($($arg)*)));
+    }};
+}
+
+/// Implementation of the [`print!`](crate::print) and
+/// [`println!`](crate::println) macros.
+///
+/// # Panics
+/// If writing to the hosts stdout sink fails.
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    if let Err(error) = write_fmt(args, stdout_write_all) {
+        panic!("failed printing to stdout: {error:?}");
+    }
+}
+
+/// Implementation of the [`eprint!`](crate::eprint) and
+/// [`eprintln!`](crate::eprintln) macros.
+///
+/// # Panics
+/// If writing to the hosts stderr sink fails.
+#[doc(hidden)]
+pub fn _eprint(args: fmt::Arguments) {
+    if let Err(error) = write_fmt(args, stderr_write_all) {
+        panic!("failed printing to stderr: {error:?}");
+    }
+}
+
+/// Format `args` sending the result, in chunks, to `write_all`.
+///
+/// # Arguments
+/// * `args` - The arguments to format.
+/// * `write_all` - Where to send the formatted chunks.
+///
+/// # Errors
+/// When `write_all` fails. No more chunks are sent after a failure.
+///
+/// # Panics
+/// If a formatting trait implementation returns an error.
+fn write_fmt<F>(args: fmt::Arguments, write_all: F) -> Result<(), Error>
+where
+    F: FnMut(&[u8]) -> Result<(), Error>,
+{
+    let mut writer = FlushingWriter::new(write_all);
+    match writer.write_fmt(args) {
+        Ok(()) => writer.flush(),
+        Err(_) => match writer.sink_error() {
+            Some(error) => Err(error),
+            None => panic!("a formatting trait implementation returned an error"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::*;
+    use crate::write_buffer::BUFFER_SIZE;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[test]
+    fn long_message_is_sent_in_chunks() {
+        let message = "a".repeat(BUFFER_SIZE * 2 + 1);
+        let mut chunks = Vec::new();
+
+        write_fmt(format_args!("{message}"), |bytes| {
+            chunks.push(String::from_utf8(bytes.to_vec()).expect("Chunk should be valid UTF-8"));
+            Ok(())
+        })
+        .expect("Shouldn't fail to write message");
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), message);
+    }
+
+    #[test]
+    fn failed_chunk_stops_writing() {
+        let message = "c".repeat(BUFFER_SIZE * 3);
+        let mut calls = 0;
+
+        let error = write_fmt(format_args!("{message}"), |_| {
+            calls += 1;
+            Err(Error::FileBadStatus)
+        })
+        .unwrap_err();
+
+        assert_eq!(error, Error::FileBadStatus);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "a formatting trait implementation returned an error")]
+    fn failed_formatting_panics() {
+        struct Failing;
+        impl fmt::Display for Failing {
+            fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let _ = write_fmt(format_args!("{Failing}"), |_| Ok(()));
+    }
+}
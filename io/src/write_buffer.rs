@@ -4,35 +4,163 @@
 
 use core::fmt;
 
-/// Byte size of [`WriteBuffer`].
+/// Default byte size of [`WriteBuffer`].
 ///
 /// Attempting to write more than this many bytes to the [`WriteBuffer`] will
 /// result in an error.
 pub const BUFFER_SIZE: usize = 4096;
 
+/// Appended to the contents of a lossy [`WriteBuffer`] when they have been
+/// truncated.
+pub const TRUNCATION_MARKER: &str = "...";
+
 /// A buffer which implements the [`fmt::Write`] trait.
+///
+/// The buffer holds at most `N` bytes, [`BUFFER_SIZE`] unless created with
+/// [`WriteBuffer::new_sized()`]. By default, attempting to write more than `N`
+/// bytes results in an error. A lossy buffer, created with
+/// [`WriteBuffer::new_lossy()`] or [`WriteBuffer::new_sized_lossy()`], will
+/// instead truncate the contents, on a UTF-8 character boundary, and end them
+/// with the [`TRUNCATION_MARKER`].
+///
+/// # Examples
+///
+/// ```
+/// use core::fmt::Write;
+/// use mc_sgx_io::WriteBuffer;
+///
+/// let mut buffer = WriteBuffer::<16>::new_sized_lossy();
+/// let size = 16;
+/// write!(buffer, "This is longer than {size} bytes").unwrap();
+///
+/// let contents: &str = buffer.as_ref();
+/// assert_eq!(contents, "This is longe...");
+/// assert!(buffer.is_truncated());
+/// ```
 #[derive(Debug)]
-pub struct WriteBuffer {
-    buf: [u8; BUFFER_SIZE],
+pub struct WriteBuffer<const N: usize = BUFFER_SIZE> {
+    buf: [u8; N],
     pos: usize,
+    lossy: bool,
+    truncated: bool,
 }
 
-impl WriteBuffer {
+impl WriteBuffer<BUFFER_SIZE> {
     /// Create a new empty [`WriteBuffer`]
+    ///
+    /// Writing more than [`BUFFER_SIZE`] bytes will result in an error.
     pub const fn new() -> Self {
+        Self::new_sized()
+    }
+
+    /// Create a new empty lossy [`WriteBuffer`]
+    ///
+    /// Writing more than [`BUFFER_SIZE`] bytes will truncate the contents, see
+    /// [`WriteBuffer::new_sized_lossy()`].
+    pub const fn new_lossy() -> Self {
+        Self::new_sized_lossy()
+    }
+}
+
+impl<const N: usize> WriteBuffer<N> {
+    /// Create a new empty [`WriteBuffer`] holding at most `N` bytes
+    ///
+    /// Writing more than the capacity will result in an error.
+    pub const fn new_sized() -> Self {
         WriteBuffer {
-            buf: [0; BUFFER_SIZE],
+            buf: [0; N],
             pos: 0,
+            lossy: false,
+            truncated: false,
+        }
+    }
+
+    /// Create a new empty lossy [`WriteBuffer`] holding at most `N` bytes
+    ///
+    /// Writing more than the capacity will truncate the contents, on a UTF-8
+    /// character boundary, and end them with the [`TRUNCATION_MARKER`]. Once
+    /// truncated, subsequent writes are ignored until the buffer is cleared.
+    pub const fn new_sized_lossy() -> Self {
+        WriteBuffer {
+            buf: [0; N],
+            pos: 0,
+            lossy: true,
+            truncated: false,
         }
     }
 
     /// Clear the contents in the [`WriteBuffer`]
     pub fn clear(&mut self) {
         self.pos = 0;
+        self.truncated = false;
+    }
+
+    /// The maximum number of bytes the [`WriteBuffer`] can hold
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// The number of bytes that can still be written to the [`WriteBuffer`]
+    pub const fn remaining(&self) -> usize {
+        N - self.pos
+    }
+
+    /// The number of bytes in the [`WriteBuffer`]
+    pub const fn len(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns `true` if the [`WriteBuffer`] has no contents
+    pub const fn is_empty(&self) -> bool {
+        self.pos == 0
+    }
+
+    /// Returns `true` if the contents of a lossy [`WriteBuffer`] have been
+    /// truncated
+    pub const fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Append `bytes`, which must fit in the remaining space
+    fn append(&mut self, bytes: &[u8]) {
+        self.buf[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    /// Append as much of `string` as fits, followed by the
+    /// [`TRUNCATION_MARKER`]. Existing contents are cut back when there isn't
+    /// room for the marker.
+    fn truncate_with(&mut self, string: &str) {
+        let marker = &TRUNCATION_MARKER[..TRUNCATION_MARKER.len().min(N)];
+        let cut = N - marker.len();
+
+        if cut >= self.pos {
+            let mut end = cut - self.pos;
+            while !string.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.append(&string.as_bytes()[..end]);
+        } else {
+            let contents: &str = self.as_ref();
+            let mut end = cut;
+            while !contents.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.pos = end;
+        }
+
+        self.append(marker.as_bytes());
+        self.truncated = true;
     }
 }
 
-impl AsRef<str> for WriteBuffer {
+impl<const N: usize> Default for WriteBuffer<N> {
+    fn default() -> Self {
+        Self::new_sized()
+    }
+}
+
+impl<const N: usize> AsRef<str> for WriteBuffer<N> {
     fn as_ref(&self) -> &str {
         // Shouldn't fail because [`Write::write_str()`] is the only public way
         // to add content. [`Write::write_str()`] takes a `&str` so for this to
@@ -43,25 +171,30 @@ impl AsRef<str> for WriteBuffer {
     }
 }
 
-impl AsRef<[u8]> for WriteBuffer {
+impl<const N: usize> AsRef<[u8]> for WriteBuffer<N> {
     fn as_ref(&self) -> &[u8] {
         &self.buf[..self.pos]
     }
 }
 
-impl fmt::Write for WriteBuffer {
+impl<const N: usize> fmt::Write for WriteBuffer<N> {
     fn write_str(&mut self, string: &str) -> fmt::Result {
-        let bytes = string.as_bytes();
+        if self.truncated {
+            return Ok(());
+        }
 
-        let remaining = &mut self.buf[self.pos..];
-        if remaining.len() < bytes.len() {
-            return Err(fmt::Error);
+        let bytes = string.as_bytes();
+        if self.remaining() < bytes.len() {
+            return match self.lossy {
+                true => {
+                    self.truncate_with(string);
+                    Ok(())
+                }
+                false => Err(fmt::Error),
+            };
         }
 
-        let new_contents = &mut remaining[..bytes.len()];
-        new_contents.copy_from_slice(bytes);
-
-        self.pos += bytes.len();
+        self.append(bytes);
 
         Ok(())
     }
@@ -154,4 +287,100 @@ mod test {
 
         assert!(buffer.write_str("D").is_err());
     }
+
+    #[test]
+    fn default_capacity() {
+        let buffer = WriteBuffer::new();
+
+        assert_eq!(buffer.capacity(), BUFFER_SIZE);
+        assert_eq!(buffer.remaining(), BUFFER_SIZE);
+        assert_eq!(buffer.len(), 0);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn sizes_track_contents() {
+        let mut buffer = WriteBuffer::<10>::new_sized();
+
+        buffer
+            .write_str("1234")
+            .expect("Shouldn't fail to write message");
+
+        assert_eq!(buffer.capacity(), 10);
+        assert_eq!(buffer.remaining(), 6);
+        assert_eq!(buffer.len(), 4);
+        assert!(!buffer.is_empty());
+
+        buffer.clear();
+
+        assert_eq!(buffer.remaining(), 10);
+        assert_eq!(buffer.len(), 0);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn small_buffer_errors_when_full() {
+        let mut buffer = WriteBuffer::<4>::new_sized();
+
+        buffer
+            .write_str("1234")
+            .expect("Shouldn't fail to write message");
+
+        assert!(buffer.write_str("5").is_err());
+        let contents: &str = buffer.as_ref();
+        assert_eq!(contents, "1234");
+    }
+
+    #[parameterized(
+    fits = {&["hello"], "hello", false},
+    exactly_fits = {&["hello", " worl"], "hello worl", false},
+    one_too_many = {&["hello world"], "hello w...", true},
+    second_write_overflows = {&["hello", " world"], "hello w...", true},
+    overflows_existing_contents = {&["hello wor", "ld"], "hello w...", true},
+    writes_after_truncation_ignored = {&["hello world", "!"], "hello w...", true},
+    multi_byte_boundary = {&["hello €€"], "hello ...", true},
+    multi_byte_straddles_cut = {&["hello€world"], "hello...", true},
+    )]
+    fn lossy_buffer_truncates(messages: &[&str], expected: &str, truncated: bool) {
+        let mut buffer = WriteBuffer::<10>::new_sized_lossy();
+
+        for message in messages {
+            buffer
+                .write_str(message)
+                .expect("Lossy buffer shouldn't fail to write");
+        }
+
+        let contents: &str = buffer.as_ref();
+        assert_eq!(contents, expected);
+        assert_eq!(buffer.is_truncated(), truncated);
+    }
+
+    #[test]
+    fn lossy_buffer_smaller_than_marker() {
+        let mut buffer = WriteBuffer::<2>::new_sized_lossy();
+
+        buffer
+            .write_str("hello")
+            .expect("Lossy buffer shouldn't fail to write");
+
+        let contents: &str = buffer.as_ref();
+        assert_eq!(contents, "..");
+    }
+
+    #[test]
+    fn clearing_lossy_buffer_resets_truncation() {
+        let mut buffer = WriteBuffer::<10>::new_sized_lossy();
+        buffer
+            .write_str("hello world")
+            .expect("Lossy buffer shouldn't fail to write");
+
+        buffer.clear();
+        buffer
+            .write_str("again")
+            .expect("Lossy buffer shouldn't fail to write");
+
+        let contents: &str = buffer.as_ref();
+        assert_eq!(contents, "again");
+        assert!(!buffer.is_truncated());
+    }
 }
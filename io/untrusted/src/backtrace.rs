@@ -0,0 +1,193 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Symbolizes the backtraces written by the enclave's panic handler, from the
+//! `backtrace` feature of `mc-sgx-panic`.
+
+use crate::default_stderr_write_all;
+use addr2line::object::File;
+use addr2line::ObjectContext;
+use std::fmt::Write;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The first line of a backtrace from the enclave
+const HEADER: &str = "enclave backtrace:";
+
+/// Symbolizes the backtraces from an enclave against the enclave's debug
+/// information.
+///
+/// The backtraces are in terms of the addresses in the unsigned enclave ELF.
+/// The signed enclave is missing the debug information needed to symbolize.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mc_sgx_io_untrusted::{stderr_sink, Symbolizer};
+///
+/// let symbolizer = Symbolizer::new("enclave.so").unwrap();
+/// let symbolizer: &'static Symbolizer = Box::leak(Box::new(symbolizer));
+/// stderr_sink(Box::leak(Box::new(move |buf: &[u8]| symbolizer.write_all(buf))));
+/// ```
+pub struct Symbolizer {
+    context: ObjectContext,
+}
+
+impl Symbolizer {
+    /// Create a [`Symbolizer`] for an enclave
+    ///
+    /// # Arguments
+    /// * `enclave` - The path to the unsigned enclave ELF
+    ///
+    /// # Errors
+    /// When the enclave can't be read, or its debug information can't be
+    /// parsed.
+    pub fn new(enclave: impl AsRef<Path>) -> Result<Self, Error> {
+        let contents = std::fs::read(enclave)?;
+        let object = File::parse(contents.as_slice())
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+        let context = ObjectContext::new(&object)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+        Ok(Self { context })
+    }
+
+    /// A [`WriteAll`](crate::WriteAll) function which symbolizes any
+    /// backtraces from the enclave.
+    ///
+    /// The symbolized backtraces, and any other messages, are directed to
+    /// [`std::io::stderr`].
+    pub fn write_all(&self, buf: &[u8]) {
+        match std::str::from_utf8(buf).ok().and_then(parse_backtrace) {
+            Some(addresses) => {
+                default_stderr_write_all(self.symbolize(&addresses).as_bytes());
+            }
+            None => default_stderr_write_all(buf),
+        }
+    }
+
+    /// Symbolize the `addresses` of a backtrace.
+    ///
+    /// Each frame lists the functions, including inlined functions, and the
+    /// source locations for the address. Addresses that can't be symbolized
+    /// are listed as is.
+    ///
+    /// # Arguments
+    /// * `addresses` - The return addresses of the backtrace, relative to the
+    ///   enclave base.
+    pub fn symbolize(&self, addresses: &[u64]) -> String {
+        let mut backtrace = format!("{HEADER}\n");
+        for (index, address) in addresses.iter().enumerate() {
+            // Ignore the results, writing to a `String` doesn't fail
+            let _ = writeln!(backtrace, "{index:>4}: {address:#x}");
+            // The return address is the instruction after the call, the call
+            // is the location of interest.
+            let probe = address.saturating_sub(1);
+            let Ok(mut frames) = self.context.find_frames(probe) else {
+                continue;
+            };
+            while let Ok(Some(frame)) = frames.next() {
+                let function = frame
+                    .function
+                    .as_ref()
+                    .and_then(|function| function.demangle().ok());
+                let _ = writeln!(
+                    backtrace,
+                    "          {}",
+                    function.as_deref().unwrap_or("<unknown>")
+                );
+                if let Some(location) = frame.location {
+                    let _ = write!(
+                        backtrace,
+                        "            at {}",
+                        location.file.unwrap_or("??")
+                    );
+                    if let Some(line) = location.line {
+                        let _ = write!(backtrace, ":{line}");
+                        if let Some(column) = location.column {
+                            let _ = write!(backtrace, ":{column}");
+                        }
+                    }
+                    backtrace.push('\n');
+                }
+            }
+        }
+        backtrace
+    }
+}
+
+impl std::fmt::Debug for Symbolizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Symbolizer").finish_non_exhaustive()
+    }
+}
+
+/// Parse the addresses of a backtrace written by the enclave.
+///
+/// The backtraces are formatted as:
+///
+/// ```text
+/// enclave backtrace:
+///    0: 0x<address>
+///    1: 0x<address>
+/// ```
+///
+/// # Returns
+/// `None` if `message` isn't a backtrace.
+fn parse_backtrace(message: &str) -> Option<Vec<u64>> {
+    let mut lines = message.lines();
+    if lines.next()? != HEADER {
+        return None;
+    }
+    lines
+        .enumerate()
+        .map(|(expected, line)| {
+            let (index, address) = line.trim_start().split_once(": ")?;
+            if index.parse::<usize>().ok()? != expected {
+                return None;
+            }
+            u64::from_str_radix(address.strip_prefix("0x")?, 16).ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use yare::parameterized;
+
+    #[test]
+    fn backtrace_addresses() {
+        let addresses = parse_backtrace("enclave backtrace:\n   0: 0x1a2b\n   1: 0x30\n");
+
+        assert_eq!(addresses, Some(vec![0x1a2b, 0x30]));
+    }
+
+    #[test]
+    fn empty_backtrace() {
+        assert_eq!(parse_backtrace("enclave backtrace:\n"), Some(vec![]));
+    }
+
+    #[test]
+    fn wide_frame_index() {
+        let mut message = String::from("enclave backtrace:\n");
+        for index in 0..12 {
+            message.push_str(&format!("{index:>4}: 0x{index:x}\n"));
+        }
+
+        let addresses = parse_backtrace(&message).expect("Should be a backtrace");
+
+        assert_eq!(addresses, (0..12).collect::<Vec<_>>());
+    }
+
+    #[parameterized(
+    no_header = {"   0: 0x1a2b\n"},
+    other_header = {"stack backtrace:\n   0: 0x1a2b\n"},
+    decimal_address = {"enclave backtrace:\n   0: 1234\n"},
+    bad_address = {"enclave backtrace:\n   0: 0xnope\n"},
+    skipped_index = {"enclave backtrace:\n   0: 0x10\n   2: 0x20\n"},
+    missing_index = {"enclave backtrace:\n0x10\n"},
+    panic_message = {"panicked at 'oh no', src/lib.rs:1:1"},
+    )]
+    fn not_a_backtrace(message: &str) {
+        assert_eq!(parse_backtrace(message), None);
+    }
+}
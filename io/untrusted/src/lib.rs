@@ -2,12 +2,22 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs, missing_debug_implementations)]
 
-//! Module for providing stderr IO from an enclave.
+//! Module for providing stderr and stdout IO from an enclave.
 //!
-//! By default stderr from an enclave will be directed to the untrusted (host)
-//! stderr. Consumers can redirect this stream by providing a [`WriteAll`]
-//! function via [`stderr_sink`].
-
+//! By default stderr and stdout from an enclave will be directed to the
+//! untrusted (host) stderr and stdout respectively. Consumers can redirect
+//! these streams by providing a [`WriteAll`] function via [`stderr_sink`] or
+//! [`stdout_sink`].
+
+#[cfg(feature = "backtrace")]
+mod backtrace;
+#[cfg(feature = "log")]
+mod logger;
+
+#[cfg(feature = "backtrace")]
+pub use backtrace::Symbolizer;
+#[cfg(feature = "log")]
+pub use logger::{log_sink, LogSink};
 use once_cell::sync::Lazy;
 use std::ffi::c_void;
 use std::io::Write;
@@ -28,6 +38,15 @@ pub fn stderr_sink(write_all: &'static WriteAll) {
     stderr.write_all = write_all;
 }
 
+/// Specify the function to use for stdout messages
+///
+/// # Arguments
+/// * `write_all` - The function to use for writing stdout from the enclave
+pub fn stdout_sink(write_all: &'static WriteAll) {
+    let mut stdout = STDOUT.lock().expect("Mutex has been poisoned");
+    stdout.write_all = write_all;
+}
+
 /// Wraps the `write_all` function in a struct so that we can implement the
 /// `Send` trait.
 struct Stream {
@@ -45,6 +64,13 @@ static STDERR: Lazy<Mutex<Stream>> = Lazy::new(|| {
     })
 });
 
+/// The stdout stream to use for the `ocall_stdout`
+static STDOUT: Lazy<Mutex<Stream>> = Lazy::new(|| {
+    Mutex::new(Stream {
+        write_all: &default_stdout_write_all,
+    })
+});
+
 /// A ['WriteAll] function that directs to [`std::io::stderr`]
 fn default_stderr_write_all(buf: &[u8]) {
     std::io::stderr()
@@ -52,6 +78,13 @@ fn default_stderr_write_all(buf: &[u8]) {
         .expect("Failed writing to stderr");
 }
 
+/// A ['WriteAll] function that directs to [`std::io::stdout`]
+fn default_stdout_write_all(buf: &[u8]) {
+    std::io::stdout()
+        .write_all(buf)
+        .expect("Failed writing to stdout");
+}
+
 #[no_mangle]
 /// The ocall that will take in stderr messages from the enclave.
 extern "C" fn ocall_stderr(input: *const c_void, len: usize) {
@@ -63,29 +96,60 @@ extern "C" fn ocall_stderr(input: *const c_void, len: usize) {
     (stderr.write_all)(bytes)
 }
 
+#[no_mangle]
+/// The ocall that will take in stdout messages from the enclave.
+extern "C" fn ocall_stdout(input: *const c_void, len: usize) {
+    // SAFETY: Converting from C interface to Rust. We must rely on the enclave
+    // side of the implementation to provide the correct length for the input
+    // buffer
+    let bytes = unsafe { slice::from_raw_parts(input as *const u8, len) };
+    let stdout = STDOUT.lock().expect("Mutex has been poisoned");
+    (stdout.write_all)(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use mc_sgx_urts::EnclaveBuilder;
     use mc_sgx_util::ResultInto;
     use serial_test::serial;
-    use test_enclave::{ecall_round_trip_to_stderr, ENCLAVE};
+    use test_enclave::{ecall_round_trip_to_stderr, ecall_round_trip_to_stdout, ENCLAVE};
+
+    static TEST_STDERR: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+    static TEST_STDOUT: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
 
-    static TEST_STREAM: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
-    fn test_stream_write_all(message: &[u8]) {
-        let mut error_string = TEST_STREAM.lock().expect("Mutex has been poisoned");
-        error_string.clear();
-        error_string.push_str(std::str::from_utf8(message).unwrap());
+    fn test_stream_write_all(stream: &Mutex<String>, message: &[u8]) {
+        let mut stream_string = stream.lock().expect("Mutex has been poisoned");
+        stream_string.clear();
+        stream_string.push_str(std::str::from_utf8(message).unwrap());
     }
 
-    fn test_stream_contents() -> String {
-        TEST_STREAM.lock().expect("Mutex has been poisoned").clone()
+    fn test_stderr_write_all(message: &[u8]) {
+        test_stream_write_all(&TEST_STDERR, message);
+    }
+
+    fn test_stdout_write_all(message: &[u8]) {
+        test_stream_write_all(&TEST_STDOUT, message);
+    }
+
+    fn test_stream_contents(stream: &Mutex<String>) -> String {
+        stream.lock().expect("Mutex has been poisoned").clone()
+    }
+
+    /// Capture both stderr and stdout, starting out empty, so that tests can
+    /// tell which stream a message went to.
+    fn capture_streams() {
+        for stream in [&TEST_STDERR, &TEST_STDOUT] {
+            stream.lock().expect("Mutex has been poisoned").clear();
+        }
+        stderr_sink(&test_stderr_write_all);
+        stdout_sink(&test_stdout_write_all);
     }
 
     #[test]
     #[serial]
     fn one_line_error_message() {
-        stderr_sink(&test_stream_write_all);
+        capture_streams();
         let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
         let id = enclave.id();
 
@@ -95,14 +159,15 @@ mod tests {
         }
         .into_result()
         .unwrap();
-        let output = test_stream_contents();
+        let output = test_stream_contents(&TEST_STDERR);
         assert_eq!(output.as_str(), "a one liner");
+        assert_eq!(test_stream_contents(&TEST_STDOUT), "");
     }
 
     #[test]
     #[serial]
     fn multi_line_error_message() {
-        stderr_sink(&test_stream_write_all);
+        capture_streams();
         let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
         let id = enclave.id();
 
@@ -112,7 +177,44 @@ mod tests {
         }
         .into_result()
         .unwrap();
-        let output = test_stream_contents();
+        let output = test_stream_contents(&TEST_STDERR);
+        assert_eq!(output.as_str(), "this is\nmulti line\n");
+        assert_eq!(test_stream_contents(&TEST_STDOUT), "");
+    }
+
+    #[test]
+    #[serial]
+    fn one_line_output_message() {
+        capture_streams();
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = enclave.id();
+
+        let message = b"a one liner";
+        unsafe {
+            ecall_round_trip_to_stdout(*id, message.as_ptr() as *const c_void, message.len())
+        }
+        .into_result()
+        .unwrap();
+        let output = test_stream_contents(&TEST_STDOUT);
+        assert_eq!(output.as_str(), "a one liner");
+        assert_eq!(test_stream_contents(&TEST_STDERR), "");
+    }
+
+    #[test]
+    #[serial]
+    fn multi_line_output_message() {
+        capture_streams();
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = enclave.id();
+
+        let message = b"this is\nmulti line\n";
+        unsafe {
+            ecall_round_trip_to_stdout(*id, message.as_ptr() as *const c_void, message.len())
+        }
+        .into_result()
+        .unwrap();
+        let output = test_stream_contents(&TEST_STDOUT);
         assert_eq!(output.as_str(), "this is\nmulti line\n");
+        assert_eq!(test_stream_contents(&TEST_STDERR), "");
     }
 }
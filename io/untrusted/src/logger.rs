@@ -0,0 +1,216 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Re-emits the records from the enclave's `log` backend, in
+//! `mc_sgx_io::logger`, into the host's [`log`] implementation.
+
+use log::{Level, Record};
+use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::ffi::c_char;
+use std::slice;
+use std::sync::Mutex;
+
+/// A function that receives the log messages from the enclave.
+///
+/// The arguments are the level, the target, the module path, if the enclave
+/// knew it, and the message.
+pub type LogSink = dyn Fn(Level, &str, Option<&str>, &str);
+
+/// Specify the function to use for log messages from `ocall_log()`
+///
+/// By default the messages are re-emitted into the host's [`log`]
+/// implementation.
+///
+/// # Arguments
+/// * `sink` - The function to use for log messages from the enclave
+pub fn log_sink(sink: &'static LogSink) {
+    let mut log = LOG.lock().expect("Mutex has been poisoned");
+    log.sink = sink;
+}
+
+/// Wraps the `sink` function in a struct so that we can implement the `Send`
+/// trait.
+struct Sink {
+    sink: &'static LogSink,
+}
+
+/// SAFETY: The [`Sink`] is local to this crate and will be enclosed in a
+/// Mutex so is safe to make `Send`.
+unsafe impl Send for Sink {}
+
+/// The sink to use for the `ocall_log`
+static LOG: Lazy<Mutex<Sink>> = Lazy::new(|| {
+    Mutex::new(Sink {
+        sink: &default_log_sink,
+    })
+});
+
+/// A [`LogSink`] that re-emits the messages into the host's [`log`]
+/// implementation.
+fn default_log_sink(level: Level, target: &str, module_path: Option<&str>, message: &str) {
+    log::logger().log(
+        &Record::builder()
+            .level(level)
+            .target(target)
+            .module_path(module_path)
+            .args(format_args!("{message}"))
+            .build(),
+    );
+}
+
+/// Convert the `level` from `ocall_log()` into a [`Level`].
+///
+/// Unknown levels are treated as [`Level::Error`] so that the message isn't
+/// lost.
+fn level_from_u32(level: u32) -> Level {
+    match level {
+        2 => Level::Warn,
+        3 => Level::Info,
+        4 => Level::Debug,
+        5 => Level::Trace,
+        _ => Level::Error,
+    }
+}
+
+/// Convert a buffer from the enclave into a string.
+///
+/// # Safety
+/// `ptr` must point to at least `len` bytes.
+unsafe fn lossy_str<'a>(ptr: *const c_char, len: usize) -> Cow<'a, str> {
+    let bytes = slice::from_raw_parts(ptr as *const u8, len);
+    String::from_utf8_lossy(bytes)
+}
+
+#[no_mangle]
+/// The ocall that will take in log messages from the enclave.
+extern "C" fn ocall_log(
+    level: u32,
+    target: *const c_char,
+    target_len: usize,
+    module_path: *const c_char,
+    module_path_len: usize,
+    msg: *const c_char,
+    msg_len: usize,
+) {
+    // SAFETY: Converting from C interface to Rust. We must rely on the enclave
+    // side of the implementation to provide the correct lengths for the input
+    // buffers
+    let target = unsafe { lossy_str(target, target_len) };
+    let module_path = unsafe { lossy_str(module_path, module_path_len) };
+    let message = unsafe { lossy_str(msg, msg_len) };
+    // The enclave sends an empty module path when it doesn't know it
+    let module_path = (!module_path.is_empty()).then_some(&*module_path);
+    let log = LOG.lock().expect("Mutex has been poisoned");
+    (log.sink)(level_from_u32(level), &target, module_path, &message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mc_sgx_urts::EnclaveBuilder;
+    use mc_sgx_util::ResultInto;
+    use serial_test::serial;
+    use test_enclave::{ecall_round_trip_to_log, ENCLAVE};
+    use yare::parameterized;
+
+    type LogFields = (Level, String, Option<String>, String);
+
+    static TEST_LOG: Lazy<Mutex<Vec<LogFields>>> = Lazy::new(|| Mutex::new(Vec::new()));
+    fn test_log_sink(level: Level, target: &str, module_path: Option<&str>, message: &str) {
+        let mut log = TEST_LOG.lock().expect("Mutex has been poisoned");
+        log.push((
+            level,
+            target.to_string(),
+            module_path.map(str::to_string),
+            message.to_string(),
+        ));
+    }
+
+    fn round_trip_to_log(
+        level: u32,
+        target: &str,
+        module_path: &str,
+        message: &str,
+    ) -> Vec<LogFields> {
+        TEST_LOG.lock().expect("Mutex has been poisoned").clear();
+        log_sink(&test_log_sink);
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = enclave.id();
+
+        unsafe {
+            ecall_round_trip_to_log(
+                *id,
+                level,
+                target.as_ptr() as *const c_char,
+                target.len(),
+                module_path.as_ptr() as *const c_char,
+                module_path.len(),
+                message.as_ptr() as *const c_char,
+                message.len(),
+            )
+        }
+        .into_result()
+        .unwrap();
+        TEST_LOG.lock().expect("Mutex has been poisoned").clone()
+    }
+
+    #[test]
+    #[serial]
+    fn log_from_enclave_keeps_level() {
+        for (level, expected) in [
+            (1, Level::Error),
+            (2, Level::Warn),
+            (3, Level::Info),
+            (4, Level::Debug),
+            (5, Level::Trace),
+        ] {
+            let log = round_trip_to_log(level, "enclave", "enclave::module", "a message");
+
+            assert_eq!(
+                log,
+                [(
+                    expected,
+                    "enclave".into(),
+                    Some("enclave::module".into()),
+                    "a message".into()
+                )]
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn multi_line_log_from_enclave() {
+        let log = round_trip_to_log(3, "enclave", "enclave", "this is\nmulti line\n");
+
+        assert_eq!(
+            log,
+            [(
+                Level::Info,
+                "enclave".into(),
+                Some("enclave".into()),
+                "this is\nmulti line\n".into()
+            )]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn empty_module_path_from_enclave_is_none() {
+        let log = round_trip_to_log(3, "enclave", "", "a message");
+
+        assert_eq!(
+            log,
+            [(Level::Info, "enclave".into(), None, "a message".into())]
+        );
+    }
+
+    #[parameterized(
+    zero = {0},
+    six = {6},
+    max = {u32::MAX},
+    )]
+    fn unknown_levels_are_errors(level: u32) {
+        assert_eq!(level_from_u32(level), Level::Error);
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Reporting of a backtrace to the host during a panic
+//!
+//! The backtrace is found by walking the frame pointer chain, so the enclave
+//! must be built with `-C force-frame-pointers=yes`. Each frame is written as
+//! the return address relative to the enclave base, which is the virtual
+//! address in the unsigned enclave ELF. The host can symbolize the addresses
+//! against that ELF, see `mc-sgx-io-untrusted`.
+//!
+//! The backtrace is formatted as:
+//!
+//! ```text
+//! enclave backtrace:
+//!    0: 0x<address>
+//!    1: 0x<address>
+//! ```
+
+use core::arch::asm;
+use core::ffi::{c_int, c_void};
+use core::fmt::Write;
+use core::mem;
+use mc_sgx_io::WriteBuffer;
+
+/// The first line of the backtrace, used by the host to recognize it
+const HEADER: &str = "enclave backtrace:";
+
+/// The most frames to report.
+///
+/// Limits the size of the backtrace so that it fits in one [`WriteBuffer`].
+const MAX_FRAMES: usize = 64;
+
+/// The first two words of a frame, as pushed by a function's prologue when
+/// using frame pointers.
+#[repr(C)]
+struct Frame {
+    /// The frame pointer of the caller
+    previous: usize,
+    /// The address to return to in the caller
+    return_address: usize,
+}
+
+/// Write the backtrace of the current thread to the hosts stderr sink.
+///
+/// The backtrace is formatted into a lossy [`WriteBuffer`] on the stack, to
+/// avoid allocating, and sent with one ocall.
+#[inline(never)]
+pub(crate) fn report_backtrace() {
+    let frame_pointer: usize;
+    // SAFETY: Only reads the frame pointer register.
+    unsafe {
+        asm!("mov {}, rbp", out(reg) frame_pointer, options(nomem, nostack, preserves_flags));
+    }
+
+    let base = enclave_base();
+    let mut buffer = WriteBuffer::new_lossy();
+    // Ignore the results, the buffer is lossy so the worst case is a truncated
+    // backtrace
+    let _ = writeln!(buffer, "{HEADER}");
+    for (index, return_address) in return_addresses(frame_pointer).enumerate() {
+        let _ = writeln!(
+            buffer,
+            "{index:>4}: {:#x}",
+            return_address.wrapping_sub(base)
+        );
+    }
+
+    // Ignore the result, we're already panicking we can't really do much if
+    // `stderr_write_all()` fails
+    let _ = mc_sgx_io::stderr_write_all(buffer.as_ref());
+}
+
+/// Iterate over the return addresses of the frame pointer chain starting at
+/// `frame_pointer`.
+///
+/// The walk stops at the first frame which isn't a plausible frame, that is
+/// one that is null, misaligned, outside of the enclave, or not further up
+/// the stack than the previous frame.
+fn return_addresses(mut frame_pointer: usize) -> impl Iterator<Item = usize> {
+    let mut previous = 0;
+    core::iter::from_fn(move || {
+        if frame_pointer <= previous
+            || frame_pointer % mem::align_of::<Frame>() != 0
+            || !is_within_enclave(frame_pointer, mem::size_of::<Frame>())
+        {
+            return None;
+        }
+
+        // SAFETY: `frame_pointer` was checked to be aligned and within the
+        // enclave.
+        let frame = unsafe { &*(frame_pointer as *const Frame) };
+        if frame.return_address == 0 {
+            return None;
+        }
+
+        previous = frame_pointer;
+        frame_pointer = frame.previous;
+        Some(frame.return_address)
+    })
+    .take(MAX_FRAMES)
+}
+
+/// Is the range `address..address + size` entirely within the enclave
+fn is_within_enclave(address: usize, size: usize) -> bool {
+    // SAFETY: Only inspects the address range, it isn't dereferenced.
+    unsafe { sgx_is_within_enclave(address as *const c_void, size) == 1 }
+}
+
+/// The address the enclave was loaded at
+fn enclave_base() -> usize {
+    // SAFETY: Only the address of `__ImageBase` is used, it isn't read.
+    unsafe { &__ImageBase as *const u8 as usize }
+}
+
+extern "C" {
+    /// Marks the start of the enclave image, provided by the SGX SDK's tRTS
+    static __ImageBase: u8;
+
+    /// Check whether a buffer is entirely within the enclave
+    ///
+    /// # Arguments
+    /// * `addr` - The start of the buffer
+    /// * `size` - The byte length of the buffer
+    ///
+    /// # Returns
+    /// 1 when the buffer is strictly within the enclave, 0 otherwise.
+    fn sgx_is_within_enclave(addr: *const c_void, size: usize) -> c_int;
+}
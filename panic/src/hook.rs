@@ -0,0 +1,150 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Registration of a custom panic hook
+//!
+//! This is a subset of the functionality available in Rust's std
+//! [panicking.rs](https://github.com/rust-lang/rust/blob/master/library/std/src/panicking.rs)
+//! module. It deviates in that the hook is a `fn(&PanicInfo)` instead of a
+//! `Box<dyn Fn(&PanicInfo)>` so that no allocation is needed.
+
+use core::mem;
+use core::panic::PanicInfo;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use mc_sgx_panic_sys::thread;
+
+/// The signature of a panic hook
+type Hook = fn(&PanicInfo);
+
+/// The current panic hook, null when the default hook is in use.
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Registers a custom panic hook, replacing the previously registered hook.
+///
+/// The panic hook is invoked when an enclave thread panics, prior to the
+/// enclave being aborted. The hook is provided with a [`PanicInfo`] which
+/// contains information about the origin of the panic, including the payload
+/// passed to `panic!` and the source code location from which the panic
+/// originated.
+///
+/// The panic hook is a global resource. The default hook logs the panic
+/// message to the host when the `log` feature is enabled, otherwise it does
+/// nothing. The default hook can be called from a custom hook with
+/// [`default_hook()`].
+///
+/// # Panics
+///
+/// Panics if called from a panicking thread.
+///
+/// # Examples
+///
+/// ```
+/// use core::panic::PanicInfo;
+///
+/// fn hook(info: &PanicInfo) {
+///     // Zeroize secrets, record the panic reason, etc.
+///     mc_sgx_panic::default_hook(info);
+/// }
+///
+/// mc_sgx_panic::set_hook(hook);
+/// ```
+pub fn set_hook(hook: Hook) {
+    if thread::panicking() {
+        panic!("cannot modify the panic hook from a panicking thread");
+    }
+
+    HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+/// Unregisters the current panic hook and returns it, registering the default
+/// hook in its place.
+///
+/// If no custom hook is registered, the [`default_hook()`] is returned.
+///
+/// # Panics
+///
+/// Panics if called from a panicking thread.
+///
+/// # Examples
+///
+/// ```
+/// use core::panic::PanicInfo;
+///
+/// fn hook(_info: &PanicInfo) {}
+///
+/// mc_sgx_panic::set_hook(hook);
+/// let previous = mc_sgx_panic::take_hook();
+/// assert_eq!(previous as usize, hook as usize);
+/// ```
+#[must_use]
+pub fn take_hook() -> Hook {
+    if thread::panicking() {
+        panic!("cannot modify the panic hook from a panicking thread");
+    }
+
+    let hook = HOOK.swap(ptr::null_mut(), Ordering::AcqRel);
+    to_hook(hook)
+}
+
+/// The default panic hook.
+///
+/// Logs the panic information to the host when the `log` feature is enabled,
+/// otherwise it does nothing.
+pub fn default_hook(_info: &PanicInfo) {
+    #[cfg(feature = "log")]
+    crate::log::log_panic_info(_info);
+}
+
+/// Run the currently registered panic hook
+#[cfg(not(test))]
+pub(crate) fn run_hook(info: &PanicInfo) {
+    let hook = to_hook(HOOK.load(Ordering::Acquire));
+    hook(info);
+}
+
+/// Convert the value stored in [`HOOK`] back into a [`Hook`]
+fn to_hook(hook: *mut ()) -> Hook {
+    if hook.is_null() {
+        default_hook
+    } else {
+        // SAFETY: The only non null values stored in `HOOK` come from
+        // `set_hook()`, which are valid `Hook` function pointers.
+        unsafe { mem::transmute::<*mut (), Hook>(hook) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serial_test::serial;
+
+    fn custom_hook(_info: &PanicInfo) {}
+
+    fn other_hook(_info: &PanicInfo) {}
+
+    #[test]
+    #[serial]
+    fn default_hook_when_none_set() {
+        let _ = take_hook();
+
+        assert_eq!(take_hook() as usize, default_hook as usize);
+    }
+
+    #[test]
+    #[serial]
+    fn take_hook_returns_set_hook() {
+        set_hook(custom_hook);
+
+        assert_eq!(take_hook() as usize, custom_hook as usize);
+        assert_eq!(take_hook() as usize, default_hook as usize);
+    }
+
+    #[test]
+    #[serial]
+    fn set_hook_replaces_previous_hook() {
+        set_hook(custom_hook);
+        set_hook(other_hook);
+
+        assert_eq!(take_hook() as usize, other_hook as usize);
+    }
+}
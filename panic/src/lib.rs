@@ -3,18 +3,45 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs, missing_debug_implementations)]
 #![no_std]
+#![cfg_attr(
+    any(feature = "ocall-panic", feature = "unwind"),
+    feature(panic_info_message)
+)]
+#![cfg_attr(feature = "unwind", feature(core_intrinsics, lang_items))]
+
+#[cfg(all(feature = "unwind", not(test)))]
+extern crate alloc;
 
 #[cfg(not(test))]
 use core::panic::PanicInfo;
 #[cfg(not(test))]
 use mc_sgx_panic_sys::panic_count;
 
+#[cfg(all(feature = "backtrace", not(test)))]
+mod backtrace;
+mod hook;
 #[cfg(feature = "log")]
 mod log;
+#[cfg(all(feature = "ocall-panic", not(test)))]
+mod ocall;
+#[cfg(all(feature = "unwind", not(test)))]
+mod unwind;
+
+pub use hook::{default_hook, set_hook, take_hook};
+#[cfg(all(feature = "unwind", not(test)))]
+pub use unwind::{catch_ecall, catch_unwind, resume_unwind};
+
+/// Make all future panics abort, instead of unwinding.
+///
+/// This mirrors `std::panic::always_abort()`. It only changes the behavior
+/// with the `unwind` feature, otherwise panics always abort.
+pub fn always_abort() {
+    mc_sgx_panic_sys::panic_count::set_always_abort();
+}
 
 #[cfg(not(test))]
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
+fn panic(info: &PanicInfo) -> ! {
     extern "C" {
         fn abort() -> !;
     }
@@ -28,8 +55,18 @@ fn panic(_info: &PanicInfo) -> ! {
         unsafe { abort() }
     }
 
-    #[cfg(feature = "log")]
-    log::log_panic_info(_info);
+    #[cfg(feature = "ocall-panic")]
+    ocall::report_panic(info);
+
+    hook::run_hook(info);
+
+    #[cfg(feature = "backtrace")]
+    backtrace::report_backtrace();
+
+    #[cfg(feature = "unwind")]
+    if !panic_count::is_always_abort() {
+        unwind::rust_panic(unwind::payload(info));
+    }
 
     unsafe { abort() }
 }
@@ -4,32 +4,43 @@
 
 use core::fmt::Write;
 use core::panic::PanicInfo;
-use mc_sgx_io::WriteBuffer;
+use mc_sgx_core_types::Error;
+use mc_sgx_io::FlushingWriter;
 use mc_sgx_sync::Mutex;
 
-/// A buffer for building up the panic message.
+/// A writer for sending the panic message to stderr.
 /// We avoid allocating when handling the panic as failure to allocate could be
-/// the cause of the panic.
-static MESSAGE_BUFFER: Mutex<WriteBuffer> = Mutex::new(WriteBuffer::new());
+/// the cause of the panic. The message is sent in chunks as the writer's
+/// buffer fills up, so messages of any length will make it to stderr.
+static MESSAGE_WRITER: Mutex<FlushingWriter<fn(&[u8]) -> Result<(), Error>>> =
+    Mutex::new(FlushingWriter::new(mc_sgx_io::stderr_write_all));
 
 /// Log information during a panic
 ///
-/// If for some reason the `info` exceeds the size of the [`MESSAGE_BUFFER`]
-/// then this will log a default message.
+/// If for some reason the `info` fails to be formatted then this will log a
+/// default message.
 ///
 /// # Arguments:
 /// * `info` - The panic information to log
 pub(crate) fn log_panic_info(info: &PanicInfo) {
-    if let Ok(mut buffer) = MESSAGE_BUFFER.lock() {
-        buffer.clear();
-        let message = match write!(buffer, "{info}") {
-            Ok(()) => buffer.as_ref(),
-            _ => "Failed to format panic log info.",
-        };
+    if let Ok(mut writer) = MESSAGE_WRITER.lock() {
+        // The writer is shared by every panic, don't let a failure or partial
+        // message from a previous panic affect this one
+        writer.clear();
 
-        // Ignore the result, we're already panicking we can't really do much if
-        // `stderr_write_all()` fails
-        let _ = mc_sgx_io::stderr_write_all(message.as_bytes());
+        // Ignore the results, we're already panicking we can't really do much
+        // if `stderr_write_all()` fails
+        match write!(writer, "{info}") {
+            Ok(()) => {
+                let _ = writer.flush();
+            }
+            _ => {
+                let _ = writer.flush();
+                if writer.sink_error().is_none() {
+                    let _ = mc_sgx_io::stderr_write_all(b"Failed to format panic log info.");
+                }
+            }
+        }
     } else {
         let _ = mc_sgx_io::stderr_write_all(b"Mutex for panic logging has been poisoned.");
     }
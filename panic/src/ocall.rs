@@ -0,0 +1,67 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Reporting of panics to the host as a structured ocall
+
+use core::ffi::c_char;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_io::WriteBuffer;
+
+/// Report the location and message of a panic to the host via `ocall_panic()`
+///
+/// The message is formatted into a lossy [`WriteBuffer`] on the stack, to
+/// avoid allocating, so overly long messages are truncated.
+///
+/// # Arguments:
+/// * `info` - The panic information to report
+pub(crate) fn report_panic(info: &PanicInfo) {
+    let (file, line, column) = info.location().map_or(("", 0, 0), |location| {
+        (location.file(), location.line(), location.column())
+    });
+
+    let mut buffer = WriteBuffer::new_lossy();
+    if let Some(message) = info.message() {
+        // Ignore the result, a failing formatting trait implementation still
+        // leaves whatever was formatted prior to the failure.
+        let _ = write!(buffer, "{message}");
+    }
+    let message: &str = buffer.as_ref();
+
+    // Ignore the result, we're already panicking we can't really do much if
+    // the ocall fails
+    let _ = unsafe {
+        ocall_panic(
+            file.as_ptr() as *const c_char,
+            file.len(),
+            line,
+            column,
+            message.as_ptr() as *const c_char,
+            message.len(),
+        )
+    };
+}
+
+extern "C" {
+    /// The ocall to report panics to
+    ///
+    /// # Arguments
+    /// * `file` - The source file the panic originated from. Should be UTF-8.
+    /// * `file_len` - The byte length of `file`
+    /// * `line` - The line in `file` the panic originated from
+    /// * `column` - The column in `line` the panic originated from
+    /// * `msg` - The panic message. Should be UTF-8.
+    /// * `msg_len` - The byte length of `msg`
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the panic was successfully given to
+    /// the host.
+    fn ocall_panic(
+        file: *const c_char,
+        file_len: usize,
+        line: u32,
+        column: u32,
+        msg: *const c_char,
+        msg_len: usize,
+    ) -> sgx_status_t;
+}
@@ -0,0 +1,243 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Unwinding panic support
+//!
+//! This is a subset of the functionality available in Rust's std
+//! [panicking.rs](https://github.com/rust-lang/rust/blob/master/library/std/src/panicking.rs)
+//! and
+//! [panic_unwind](https://github.com/rust-lang/rust/tree/master/library/panic_unwind)
+//! crate. It deviates in that only Rust panics can be caught, any foreign
+//! exception aborts the enclave.
+
+mod abi;
+mod personality;
+
+use abi as uw;
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use core::any::Any;
+use core::intrinsics;
+use core::mem::ManuallyDrop;
+use core::panic::{PanicInfo, UnwindSafe};
+use core::ptr;
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_panic_sys::panic_count;
+
+// Provides the in-enclave unwinder, the `_Unwind_*` functions of `abi`.
+extern crate unwinding;
+
+extern "C" {
+    fn abort() -> !;
+}
+
+/// Invokes a closure, capturing the cause of an unwinding panic if one occurs.
+///
+/// This function will return `Ok` with the closure's result if the closure
+/// does not panic, and will return `Err(cause)` if the closure panics. The
+/// `cause` returned is the object with which panic was originally invoked.
+/// For panics with a message it's the formatted message as a `String`.
+///
+/// The panic hook is invoked before the panic is caught.
+///
+/// Unlike std, this function will **not** catch foreign exceptions, for
+/// example C++ exceptions, they abort the enclave.
+///
+/// # Examples
+///
+/// ```
+/// let result = mc_sgx_panic::catch_unwind(|| {
+///     panic!("oh no!");
+/// });
+/// assert!(result.is_err());
+/// ```
+pub fn catch_unwind<F, R>(f: F) -> Result<R, Box<dyn Any + Send + 'static>>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    // SAFETY: `r#try` requires a `try_fn` and `catch_fn` which correctly
+    // interpret `data`, see `do_call()` and `do_catch()`.
+    unsafe { r#try(f) }
+}
+
+/// Triggers a panic without invoking the panic hook.
+///
+/// This is designed to be used in conjunction with [`catch_unwind`] to, for
+/// example, carry a panic across a layer of C code.
+///
+/// If there is no [`catch_unwind`] to catch the panic, or [`always_abort()`]
+/// has been called, the enclave is aborted.
+///
+/// [`always_abort()`]: crate::always_abort
+pub fn resume_unwind(payload: Box<dyn Any + Send>) -> ! {
+    panic_count::increase();
+    if panic_count::is_always_abort() {
+        unsafe { abort() }
+    }
+    rust_panic(payload)
+}
+
+/// Invoke the body of an ecall, converting a panic into an error code for the
+/// host.
+///
+/// # Returns
+/// The result of `f`, or `sgx_status_t::SGX_ERROR_UNEXPECTED` if `f`
+/// panicked.
+///
+/// # Examples
+///
+/// ```
+/// use mc_sgx_core_sys_types::sgx_status_t;
+///
+/// #[no_mangle]
+/// pub extern "C" fn ecall_process(input: u32) -> sgx_status_t {
+///     mc_sgx_panic::catch_ecall(|| {
+///         assert_ne!(input, 0, "input must be non zero");
+///         sgx_status_t::SGX_SUCCESS
+///     })
+/// }
+/// ```
+pub fn catch_ecall<F>(f: F) -> sgx_status_t
+where
+    F: FnOnce() -> sgx_status_t + UnwindSafe,
+{
+    catch_unwind(f).unwrap_or(sgx_status_t::SGX_ERROR_UNEXPECTED)
+}
+
+/// The payload for a panic with `info`
+pub(crate) fn payload(info: &PanicInfo) -> Box<dyn Any + Send> {
+    let message = info
+        .message()
+        .map(|message| message.to_string())
+        .unwrap_or_default();
+    Box::new(message)
+}
+
+/// Start unwinding with `payload`.
+///
+/// The panic count must have already been increased.
+///
+/// Aborts the enclave if unwinding fails, for instance there isn't a
+/// [`catch_unwind`] to catch the panic.
+pub(crate) fn rust_panic(payload: Box<dyn Any + Send>) -> ! {
+    // SAFETY: Nothing more than raising the exception, which only returns on
+    // failure.
+    unsafe {
+        raise(payload);
+        abort()
+    }
+}
+
+/// Identifies exceptions raised by Rust, "MOZ\0RUST"
+const RUST_EXCEPTION_CLASS: uw::_Unwind_Exception_Class = u64::from_be_bytes(*b"MOZ\0RUST");
+
+/// Distinguishes the exceptions of this crate from the exceptions of another
+/// Rust runtime.
+static CANARY: u8 = 0;
+
+/// The exception which is unwound
+#[repr(C)]
+struct Exception {
+    /// The language independent header, must be first
+    header: uw::_Unwind_Exception,
+    canary: *const u8,
+    cause: Box<dyn Any + Send>,
+}
+
+/// Raise an exception carrying `payload`.
+///
+/// # Returns
+/// Only returns when unwinding failed.
+unsafe fn raise(payload: Box<dyn Any + Send>) -> uw::_Unwind_Reason_Code {
+    let exception = Box::new(Exception {
+        header: uw::_Unwind_Exception {
+            exception_class: RUST_EXCEPTION_CLASS,
+            exception_cleanup,
+            private: [0; uw::UNWINDER_PRIVATE_DATA_SIZE],
+        },
+        canary: &CANARY,
+        cause: payload,
+    });
+    let exception = Box::into_raw(exception) as *mut uw::_Unwind_Exception;
+    return uw::_Unwind_RaiseException(exception);
+
+    extern "C" fn exception_cleanup(
+        _unwind_code: uw::_Unwind_Reason_Code,
+        exception: *mut uw::_Unwind_Exception,
+    ) {
+        // SAFETY: Only invoked by the unwinder for exceptions from `raise()`.
+        let _ = unsafe { Box::from_raw(exception as *mut Exception) };
+        // This is only reached when a foreign runtime caught, and deleted, the
+        // exception. Rust panics must be rethrown.
+        unsafe { abort() }
+    }
+}
+
+/// Retrieve the payload from a caught exception.
+///
+/// Aborts the enclave when the exception wasn't raised by this crate.
+unsafe fn cleanup(exception: *mut u8) -> Box<dyn Any + Send> {
+    let exception = exception as *mut uw::_Unwind_Exception;
+    if (*exception).exception_class != RUST_EXCEPTION_CLASS {
+        uw::_Unwind_DeleteException(exception);
+        abort();
+    }
+
+    let exception = exception as *mut Exception;
+    if !ptr::eq((*exception).canary, &CANARY) {
+        abort();
+    }
+
+    let exception = Box::from_raw(exception);
+    exception.cause
+}
+
+/// Invoke `f`, catching any unwinding panic.
+///
+/// # Safety
+/// Uses the `try` intrinsic, which relies on the personality routine to find
+/// the landing pad of `do_catch()`.
+unsafe fn r#try<F, R>(f: F) -> Result<R, Box<dyn Any + Send>>
+where
+    F: FnOnce() -> R,
+{
+    // The closure goes in and either the result or the panic payload comes
+    // out.
+    union Data<F, R> {
+        f: ManuallyDrop<F>,
+        r: ManuallyDrop<R>,
+        p: ManuallyDrop<Box<dyn Any + Send>>,
+    }
+
+    let mut data = Data {
+        f: ManuallyDrop::new(f),
+    };
+    let data_ptr = &mut data as *mut _ as *mut u8;
+
+    return if intrinsics::r#try(do_call::<F, R>, data_ptr, do_catch::<F, R>) == 0 {
+        Ok(ManuallyDrop::into_inner(data.r))
+    } else {
+        Err(ManuallyDrop::into_inner(data.p))
+    };
+
+    #[inline]
+    fn do_call<F: FnOnce() -> R, R>(data: *mut u8) {
+        // SAFETY: `data` is the `Data` from `r#try()` with `f` set.
+        unsafe {
+            let data = &mut *(data as *mut Data<F, R>);
+            let f = ManuallyDrop::take(&mut data.f);
+            data.r = ManuallyDrop::new(f());
+        }
+    }
+
+    #[inline]
+    fn do_catch<F: FnOnce() -> R, R>(data: *mut u8, payload: *mut u8) {
+        // SAFETY: `data` is the `Data` from `r#try()`, `f` has been consumed
+        // by `do_call()`. `payload` is the exception given to the landing pad.
+        unsafe {
+            let data = &mut *(data as *mut Data<F, R>);
+            let payload = cleanup(payload);
+            panic_count::decrease();
+            data.p = ManuallyDrop::new(payload);
+        }
+    }
+}
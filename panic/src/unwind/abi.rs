@@ -0,0 +1,61 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! The subset of the Itanium C++ ABI, level I, unwinding interface used by the
+//! panic runtime.
+//!
+//! This is a subset of Rust's
+//! [libunwind](https://github.com/rust-lang/rust/blob/master/library/unwind/src/libunwind.rs)
+//! crate. The functions are provided by the in-enclave unwinder.
+
+#![allow(non_camel_case_types)]
+
+use core::ffi::{c_int, c_void};
+
+pub type _Unwind_Reason_Code = c_int;
+pub const _URC_NO_REASON: _Unwind_Reason_Code = 0;
+pub const _URC_FATAL_PHASE2_ERROR: _Unwind_Reason_Code = 2;
+pub const _URC_FATAL_PHASE1_ERROR: _Unwind_Reason_Code = 3;
+pub const _URC_END_OF_STACK: _Unwind_Reason_Code = 5;
+pub const _URC_HANDLER_FOUND: _Unwind_Reason_Code = 6;
+pub const _URC_INSTALL_CONTEXT: _Unwind_Reason_Code = 7;
+pub const _URC_CONTINUE_UNWIND: _Unwind_Reason_Code = 8;
+
+pub type _Unwind_Action = c_int;
+pub const _UA_SEARCH_PHASE: _Unwind_Action = 1;
+
+pub type _Unwind_Exception_Class = u64;
+
+pub type _Unwind_Exception_Cleanup_Fn =
+    extern "C" fn(unwind_code: _Unwind_Reason_Code, exception: *mut _Unwind_Exception);
+
+/// The number of words the unwinder reserves in each [`_Unwind_Exception`]
+pub const UNWINDER_PRIVATE_DATA_SIZE: usize = 6;
+
+/// The language independent header of an exception
+#[repr(C)]
+pub struct _Unwind_Exception {
+    pub exception_class: _Unwind_Exception_Class,
+    pub exception_cleanup: _Unwind_Exception_Cleanup_Fn,
+    pub private: [usize; UNWINDER_PRIVATE_DATA_SIZE],
+}
+
+/// The unwinder's state for a stack frame
+#[repr(C)]
+pub struct _Unwind_Context {
+    _private: [u8; 0],
+}
+
+/// The registers used to pass the exception to a landing pad, `RAX` and `RDX`.
+pub const UNWIND_DATA_REG: (c_int, c_int) = (0, 1);
+
+extern "C" {
+    pub fn _Unwind_RaiseException(exception: *mut _Unwind_Exception) -> _Unwind_Reason_Code;
+    pub fn _Unwind_DeleteException(exception: *mut _Unwind_Exception);
+    pub fn _Unwind_GetLanguageSpecificData(context: *mut _Unwind_Context) -> *mut c_void;
+    pub fn _Unwind_GetRegionStart(context: *mut _Unwind_Context) -> usize;
+    pub fn _Unwind_GetTextRelBase(context: *mut _Unwind_Context) -> usize;
+    pub fn _Unwind_GetDataRelBase(context: *mut _Unwind_Context) -> usize;
+    pub fn _Unwind_GetIPInfo(context: *mut _Unwind_Context, ip_before_insn: *mut c_int) -> usize;
+    pub fn _Unwind_SetGR(context: *mut _Unwind_Context, reg_index: c_int, value: usize);
+    pub fn _Unwind_SetIP(context: *mut _Unwind_Context, value: usize);
+}
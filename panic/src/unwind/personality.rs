@@ -0,0 +1,266 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! The personality routine for Rust frames.
+//!
+//! This is a subset of Rust's std
+//! [personality](https://github.com/rust-lang/rust/tree/master/library/std/src/personality)
+//! module, limited to x86_64 and DWARF based unwinding.
+//!
+//! The unwinder calls the personality routine for each frame. The routine
+//! looks up the frame's instruction pointer in the language specific data
+//! area (LSDA), generated by the compiler, to decide if the frame has a
+//! landing pad to run. Rust doesn't use the exception types, any landing pad
+//! with an action is the `catch` of [`catch_unwind()`](super::catch_unwind).
+
+use super::abi::{self as uw, _Unwind_Action, _Unwind_Context, _Unwind_Reason_Code};
+use core::ffi::c_int;
+use core::{mem, ptr};
+
+const DW_EH_PE_OMIT: u8 = 0xFF;
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+
+const DW_EH_PE_ULEB128: u8 = 0x01;
+const DW_EH_PE_UDATA2: u8 = 0x02;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_UDATA8: u8 = 0x04;
+const DW_EH_PE_SLEB128: u8 = 0x09;
+const DW_EH_PE_SDATA2: u8 = 0x0A;
+const DW_EH_PE_SDATA4: u8 = 0x0B;
+const DW_EH_PE_SDATA8: u8 = 0x0C;
+
+const DW_EH_PE_PCREL: u8 = 0x10;
+const DW_EH_PE_TEXTREL: u8 = 0x20;
+const DW_EH_PE_DATAREL: u8 = 0x30;
+const DW_EH_PE_FUNCREL: u8 = 0x40;
+const DW_EH_PE_ALIGNED: u8 = 0x50;
+
+const DW_EH_PE_INDIRECT: u8 = 0x80;
+
+/// What to do for a frame
+enum EhAction {
+    /// Nothing to run, continue unwinding
+    None,
+    /// Run the landing pad to drop the frame's values
+    Cleanup(usize),
+    /// Stop unwinding and run the landing pad
+    Catch(usize),
+    /// The frame can't unwind
+    Terminate,
+}
+
+/// The location being unwound
+struct EhContext<'a> {
+    /// The instruction pointer within the frame's function
+    ip: usize,
+    /// The start of the frame's function
+    func_start: usize,
+    get_text_start: &'a dyn Fn() -> usize,
+    get_data_start: &'a dyn Fn() -> usize,
+}
+
+#[lang = "eh_personality"]
+unsafe extern "C" fn rust_eh_personality(
+    version: c_int,
+    actions: _Unwind_Action,
+    _exception_class: uw::_Unwind_Exception_Class,
+    exception_object: *mut uw::_Unwind_Exception,
+    context: *mut _Unwind_Context,
+) -> _Unwind_Reason_Code {
+    if version != 1 {
+        return uw::_URC_FATAL_PHASE1_ERROR;
+    }
+    let eh_action = match find_eh_action(context) {
+        Ok(action) => action,
+        Err(_) => return uw::_URC_FATAL_PHASE1_ERROR,
+    };
+    if actions & uw::_UA_SEARCH_PHASE != 0 {
+        match eh_action {
+            EhAction::None | EhAction::Cleanup(_) => uw::_URC_CONTINUE_UNWIND,
+            EhAction::Catch(_) => uw::_URC_HANDLER_FOUND,
+            EhAction::Terminate => uw::_URC_FATAL_PHASE1_ERROR,
+        }
+    } else {
+        match eh_action {
+            EhAction::None => uw::_URC_CONTINUE_UNWIND,
+            EhAction::Cleanup(lpad) | EhAction::Catch(lpad) => {
+                uw::_Unwind_SetGR(context, uw::UNWIND_DATA_REG.0, exception_object as usize);
+                uw::_Unwind_SetGR(context, uw::UNWIND_DATA_REG.1, 0);
+                uw::_Unwind_SetIP(context, lpad);
+                uw::_URC_INSTALL_CONTEXT
+            }
+            EhAction::Terminate => uw::_URC_FATAL_PHASE2_ERROR,
+        }
+    }
+}
+
+/// Find the action for the frame of `context`
+unsafe fn find_eh_action(context: *mut _Unwind_Context) -> Result<EhAction, ()> {
+    let lsda = uw::_Unwind_GetLanguageSpecificData(context) as *const u8;
+    let mut ip_before_instr: c_int = 0;
+    let ip = uw::_Unwind_GetIPInfo(context, &mut ip_before_instr);
+    let eh_context = EhContext {
+        // The return address points 1 byte past the call instruction, which
+        // could be in the next IP range in the LSDA range table.
+        ip: if ip_before_instr != 0 { ip } else { ip - 1 },
+        func_start: uw::_Unwind_GetRegionStart(context),
+        get_text_start: &|| uw::_Unwind_GetTextRelBase(context),
+        get_data_start: &|| uw::_Unwind_GetDataRelBase(context),
+    };
+    parse_lsda(lsda, &eh_context)
+}
+
+/// Find the action for `context.ip` in the call site table of `lsda`
+unsafe fn parse_lsda(lsda: *const u8, context: &EhContext<'_>) -> Result<EhAction, ()> {
+    if lsda.is_null() {
+        return Ok(EhAction::None);
+    }
+
+    let func_start = context.func_start;
+    let mut reader = DwarfReader::new(lsda);
+
+    let start_encoding = reader.read::<u8>();
+    // Base address for landing pad offsets
+    let lpad_base = if start_encoding != DW_EH_PE_OMIT {
+        read_encoded_pointer(&mut reader, context, start_encoding)?
+    } else {
+        func_start
+    };
+
+    let ttype_encoding = reader.read::<u8>();
+    if ttype_encoding != DW_EH_PE_OMIT {
+        // Rust doesn't analyze exception types, so the type table is skipped
+        reader.read_uleb128();
+    }
+
+    let call_site_encoding = reader.read::<u8>();
+    let call_site_table_length = reader.read_uleb128();
+    let action_table = reader.ptr.add(call_site_table_length as usize);
+    let ip = context.ip;
+
+    while reader.ptr < action_table {
+        let cs_start = read_encoded_pointer(&mut reader, context, call_site_encoding)?;
+        let cs_len = read_encoded_pointer(&mut reader, context, call_site_encoding)?;
+        let cs_lpad = read_encoded_pointer(&mut reader, context, call_site_encoding)?;
+        let cs_action = reader.read_uleb128();
+        // The call site table is sorted by `cs_start`, so once past `ip` it
+        // won't be found.
+        if ip < func_start + cs_start {
+            break;
+        }
+        if ip < func_start + cs_start + cs_len {
+            return Ok(match (cs_lpad, cs_action) {
+                (0, _) => EhAction::None,
+                (lpad, 0) => EhAction::Cleanup(lpad_base + lpad),
+                (lpad, _) => EhAction::Catch(lpad_base + lpad),
+            });
+        }
+    }
+    // `ip` isn't in the table, this indicates a call which can't unwind
+    Ok(EhAction::Terminate)
+}
+
+/// Read a pointer from `reader` encoded with the DWARF `encoding`
+unsafe fn read_encoded_pointer(
+    reader: &mut DwarfReader,
+    context: &EhContext<'_>,
+    encoding: u8,
+) -> Result<usize, ()> {
+    if encoding == DW_EH_PE_OMIT {
+        return Err(());
+    }
+
+    // `DW_EH_PE_ALIGNED` implies it's an absolute pointer value
+    if encoding == DW_EH_PE_ALIGNED {
+        let align = mem::size_of::<usize>();
+        let offset = reader.ptr.align_offset(align);
+        reader.ptr = reader.ptr.add(offset);
+        return Ok(reader.read::<usize>());
+    }
+
+    // Relative to the address of the encoded value, despite the name
+    let pc = reader.ptr as usize;
+
+    let mut result = match encoding & 0x0F {
+        DW_EH_PE_ABSPTR => reader.read::<usize>(),
+        DW_EH_PE_ULEB128 => reader.read_uleb128() as usize,
+        DW_EH_PE_UDATA2 => reader.read::<u16>() as usize,
+        DW_EH_PE_UDATA4 => reader.read::<u32>() as usize,
+        DW_EH_PE_UDATA8 => reader.read::<u64>() as usize,
+        DW_EH_PE_SLEB128 => reader.read_sleb128() as usize,
+        DW_EH_PE_SDATA2 => reader.read::<i16>() as usize,
+        DW_EH_PE_SDATA4 => reader.read::<i32>() as usize,
+        DW_EH_PE_SDATA8 => reader.read::<i64>() as usize,
+        _ => return Err(()),
+    };
+
+    result = result.wrapping_add(match encoding & 0x70 {
+        DW_EH_PE_ABSPTR => 0,
+        DW_EH_PE_PCREL => pc,
+        DW_EH_PE_FUNCREL => {
+            if context.func_start == 0 {
+                return Err(());
+            }
+            context.func_start
+        }
+        DW_EH_PE_TEXTREL => (context.get_text_start)(),
+        DW_EH_PE_DATAREL => (context.get_data_start)(),
+        _ => return Err(()),
+    });
+
+    if encoding & DW_EH_PE_INDIRECT != 0 {
+        result = *(result as *const usize);
+    }
+
+    Ok(result)
+}
+
+/// Reads the DWARF encoded values of the LSDA
+struct DwarfReader {
+    ptr: *const u8,
+}
+
+impl DwarfReader {
+    fn new(ptr: *const u8) -> Self {
+        Self { ptr }
+    }
+
+    /// The DWARF values are packed so they may be unaligned
+    unsafe fn read<T: Copy>(&mut self) -> T {
+        let result = ptr::read_unaligned(self.ptr as *const T);
+        self.ptr = self.ptr.add(mem::size_of::<T>());
+        result
+    }
+
+    unsafe fn read_uleb128(&mut self) -> u64 {
+        let mut shift: usize = 0;
+        let mut result: u64 = 0;
+        loop {
+            let byte = self.read::<u8>();
+            result |= ((byte & 0x7F) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        result
+    }
+
+    unsafe fn read_sleb128(&mut self) -> i64 {
+        let mut shift: u32 = 0;
+        let mut result: u64 = 0;
+        let mut byte: u8;
+        loop {
+            byte = self.read::<u8>();
+            result |= ((byte & 0x7F) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        // Sign extend the result
+        if shift < u64::BITS && (byte & 0x40) != 0 {
+            result |= (!0_u64) << shift;
+        }
+        result as i64
+    }
+}
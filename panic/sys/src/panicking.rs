@@ -14,31 +14,46 @@ pub(crate) fn panicking() -> bool {
 }
 
 pub mod panic_count {
-    //! Number of panics that are currently being handled on the current thread
+    //! Number of panics that are currently being handled
     //!
     //! This deviates from
     //! [panicking.rs](https://github.com/rust-lang/rust/blob/master/library/std/src/panicking.rs)
     //! It does not:
-    //! - use `GLOBAL_PANIC_COUNT` count. The global count is meant to provide
-    //!   an optimization on some platforms. It's not clear if SGX benefits from
-    //!   this optimization and thus needing this complexity
-    //! - no `set_always_abort()`. The docs say this is for `libc::fork`.
-    //!   Enclaves do not support forking or spawning child threads or processes
+    //! - use `GLOBAL_PANIC_COUNT` as an optimization for `count_is_zero()`.
+    //!   The global count is kept separately, see [`get_global_count()`], so
+    //!   that other enclave threads can tell when any thread has panicked.
+    //! - store the always abort flag in the global count. Enclaves do not
+    //!   support forking, instead [`set_always_abort()`] is a way to opt out of
+    //!   unwinding.
     //! - use the `thread_local!` macro or the `::std::thread::LocalKey` type.
     //!   These are generic over any type that `needs_drop`, but
     //!   `usize` does not need drop
     use core::cell::Cell;
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
     /// Panic count for the current thread.
     #[thread_local]
     static LOCAL_PANIC_COUNT: Cell<usize> = Cell::new(0);
 
+    /// Panic count for all of the threads in the enclave.
+    static GLOBAL_PANIC_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// Latched once any thread in the enclave has panicked.
+    static ENCLAVE_POISONED: AtomicBool = AtomicBool::new(false);
+
+    /// Whether panics should abort instead of unwinding.
+    static ALWAYS_ABORT: AtomicBool = AtomicBool::new(false);
+
     /// Increase the number of panics that are currently happening
     ///
+    /// This also poisons the enclave, see [`is_enclave_poisoned()`].
+    ///
     /// # Returns
-    /// The number of panics that are currently happening, including this
-    /// increase.
+    /// The number of panics that are currently happening on the current
+    /// thread, including this increase.
     pub fn increase() -> usize {
+        GLOBAL_PANIC_COUNT.fetch_add(1, Ordering::Relaxed);
+        ENCLAVE_POISONED.store(true, Ordering::Release);
         let panics = LOCAL_PANIC_COUNT.get() + 1;
         LOCAL_PANIC_COUNT.set(panics);
         panics
@@ -46,10 +61,45 @@ pub mod panic_count {
 
     /// Decrease the number of panics that are currently happening
     pub fn decrease() {
+        GLOBAL_PANIC_COUNT.fetch_sub(1, Ordering::Relaxed);
         let panics = LOCAL_PANIC_COUNT.get() - 1;
         LOCAL_PANIC_COUNT.set(panics);
     }
 
+    /// Get the current number of panics that are in process on all of the
+    /// threads in the enclave
+    #[must_use]
+    pub fn get_global_count() -> usize {
+        GLOBAL_PANIC_COUNT.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` once any thread in the enclave has panicked.
+    ///
+    /// Unlike [`get_global_count()`] this remains `true` after the panic has
+    /// been handled, for instance when the panic was caught while unwinding.
+    #[must_use]
+    pub fn is_enclave_poisoned() -> bool {
+        ENCLAVE_POISONED.load(Ordering::Acquire)
+    }
+
+    /// Poison the enclave without a panic, see [`is_enclave_poisoned()`].
+    pub fn poison_enclave() {
+        ENCLAVE_POISONED.store(true, Ordering::Release);
+    }
+
+    /// Make all future panics abort, instead of unwinding.
+    ///
+    /// This is only meaningful when the panic handler supports unwinding.
+    pub fn set_always_abort() {
+        ALWAYS_ABORT.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if panics should abort, instead of unwinding.
+    #[must_use]
+    pub fn is_always_abort() -> bool {
+        ALWAYS_ABORT.load(Ordering::Relaxed)
+    }
+
     /// Get the current number of panics that are in process
     #[must_use]
     pub fn get_count() -> usize {
@@ -75,7 +125,10 @@ pub mod panic_count {
         /// should be reset to a known value on each test. This also means that
         /// there isn't a reliable way to test that 0 is the true initial value.
         fn reset_panic_count() {
-            LOCAL_PANIC_COUNT.set(0);
+            // Decrease, instead of setting, to keep the global count in sync
+            while get_count() != 0 {
+                decrease();
+            }
         }
 
         #[test]
@@ -105,9 +158,28 @@ pub mod panic_count {
             assert_eq!(get_count(), 4);
         }
 
+        #[test]
+        fn increasing_counts_globally() {
+            reset_panic_count();
+            increase();
+            assert!(get_global_count() >= 1);
+            decrease();
+        }
+
+        #[test]
+        fn increasing_poisons_enclave() {
+            reset_panic_count();
+            increase();
+            decrease();
+            assert!(is_enclave_poisoned());
+        }
+
         #[test]
         fn decrementing_one_at_a_time() {
-            LOCAL_PANIC_COUNT.set(4);
+            reset_panic_count();
+            for _ in 0..4 {
+                increase();
+            }
             assert_eq!(get_count(), 4);
             decrease();
             assert_eq!(get_count(), 3);
@@ -7,7 +7,7 @@
 //! SGX enclaves don't support many of the threading primitives so this is a
 //! very small subset of std::thread
 
-use crate::panicking;
+use crate::panicking::{self, panic_count};
 
 /// Determines whether the current thread is unwinding because of panic.
 ///
@@ -57,10 +57,53 @@ pub fn panicking() -> bool {
     panicking::panicking()
 }
 
+/// Determines whether any thread in the enclave is unwinding because of panic.
+///
+/// Unlike [`panicking()`], which is only for the current thread, this
+/// considers all of the threads in the enclave.
+#[inline]
+#[must_use]
+pub fn any_thread_panicking() -> bool {
+    panic_count::get_global_count() != 0
+}
+
+/// Determines whether any thread in the enclave has ever panicked.
+///
+/// Once a thread panics the enclave remains poisoned, even if the panic was
+/// caught. This can be checked at the start of each ecall to refuse to touch
+/// state which may have been left inconsistent by the panicking thread.
+///
+/// # Examples
+///
+/// ```
+/// use mc_sgx_panic_sys::thread;
+///
+/// #[no_mangle]
+/// pub extern "C" fn ecall_do_work() -> i32 {
+///     if thread::is_enclave_poisoned() {
+///         return -1;
+///     }
+///     // ... do the work
+///     0
+/// }
+/// ```
+#[inline]
+#[must_use]
+pub fn is_enclave_poisoned() -> bool {
+    panic_count::is_enclave_poisoned()
+}
+
+/// Poison the enclave, as if a thread had panicked.
+///
+/// See [`is_enclave_poisoned()`]. There is no way to clear the poison.
+#[inline]
+pub fn poison_enclave() {
+    panic_count::poison_enclave()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::panicking::panic_count;
 
     /// Sets panic count is 0
     /// Similar to the tests in mod `panicking::panic_count` tests need to call
@@ -99,4 +142,22 @@ mod test {
         panic_count::decrease();
         assert!(!panicking());
     }
+
+    #[test]
+    fn any_thread_panicking_when_current_thread_panicking() {
+        clear_panic_count();
+
+        panic_count::increase();
+        assert!(any_thread_panicking());
+        assert!(is_enclave_poisoned());
+
+        panic_count::decrease();
+        assert!(is_enclave_poisoned());
+    }
+
+    #[test]
+    fn poisoning_enclave_without_panic() {
+        poison_enclave();
+        assert!(is_enclave_poisoned());
+    }
 }
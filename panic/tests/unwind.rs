@@ -0,0 +1,81 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Tests for the `unwind` feature of `mc_sgx_panic` running in an enclave.
+//!
+//! The unwind enclave is built with `panic = "unwind"`, so its panics are
+//! caught instead of aborting the enclave.
+
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_urts::{Enclave, EnclaveBuilder};
+use mc_sgx_util::ResultInto;
+use test_enclave::{ecall_catch_ecall, ecall_catch_unwind, ecall_resume_unwind, ENCLAVE_UNWIND};
+
+fn create_enclave() -> Enclave {
+    EnclaveBuilder::from(ENCLAVE_UNWIND).create().unwrap()
+}
+
+fn catch_ecall(enclave: &Enclave, should_panic: bool) -> sgx_status_t {
+    let mut status = sgx_status_t::SGX_SUCCESS;
+    unsafe { ecall_catch_ecall(*enclave.id(), &mut status, should_panic.into()) }
+        .into_result()
+        .expect("Ecall failed");
+    status
+}
+
+#[test]
+fn catch_ecall_without_panic() {
+    let enclave = create_enclave();
+
+    assert_eq!(catch_ecall(&enclave, false), sgx_status_t::SGX_SUCCESS);
+}
+
+#[test]
+fn catch_ecall_turns_panic_into_error() {
+    let enclave = create_enclave();
+
+    assert_eq!(
+        catch_ecall(&enclave, true),
+        sgx_status_t::SGX_ERROR_UNEXPECTED
+    );
+}
+
+#[test]
+fn enclave_usable_after_caught_panic() {
+    let enclave = create_enclave();
+
+    // A panic which wasn't accounted for as caught would make the next panic
+    // a double panic, aborting the enclave.
+    for _ in 0..3 {
+        assert_eq!(
+            catch_ecall(&enclave, true),
+            sgx_status_t::SGX_ERROR_UNEXPECTED
+        );
+        assert_eq!(catch_ecall(&enclave, false), sgx_status_t::SGX_SUCCESS);
+    }
+}
+
+#[test]
+fn catch_unwind_returns_payload() {
+    let enclave = create_enclave();
+    let value = 0x0123_4567_89ab_cdef;
+
+    let mut caught = 0;
+    unsafe { ecall_catch_unwind(*enclave.id(), value, &mut caught) }
+        .into_result()
+        .expect("Ecall failed");
+
+    assert_eq!(caught, value);
+}
+
+#[test]
+fn resume_unwind_through_nested_catch() {
+    let enclave = create_enclave();
+    let value = 0xfedc_ba98_7654_3210;
+
+    let mut caught = 0;
+    unsafe { ecall_resume_unwind(*enclave.id(), value, &mut caught) }
+        .into_result()
+        .expect("Ecall failed");
+
+    assert_eq!(caught, value);
+}
@@ -0,0 +1,184 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+#![doc = include_str!("../README.md")]
+#![deny(missing_docs, missing_debug_implementations)]
+
+//! Module for receiving the panics of an enclave.
+//!
+//! With the `ocall-panic` feature of `mc-sgx-panic` the enclave reports each
+//! panic via `ocall_panic()` prior to aborting. By default the panics are
+//! written to [`std::io::stderr`]. Consumers can handle the panics differently
+//! by providing a [`PanicSink`] via [`panic_sink`].
+
+use once_cell::sync::Lazy;
+use std::borrow::Cow;
+use std::ffi::c_char;
+use std::fmt;
+use std::slice;
+use std::sync::Mutex;
+
+/// A panic from the enclave
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnclavePanic<'a> {
+    /// The source file the panic originated from
+    pub file: &'a str,
+    /// The line in `file` the panic originated from
+    pub line: u32,
+    /// The column in `line` the panic originated from
+    pub column: u32,
+    /// The panic message
+    pub message: &'a str,
+}
+
+impl fmt::Display for EnclavePanic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "enclave panicked at '{}', {}:{}:{}",
+            self.message, self.file, self.line, self.column
+        )
+    }
+}
+
+/// A function that receives the panics from the enclave.
+pub type PanicSink = dyn Fn(&EnclavePanic);
+
+/// Specify the function to use for panics from `ocall_panic()`
+///
+/// # Arguments
+/// * `sink` - The function to use for panics from the enclave
+pub fn panic_sink(sink: &'static PanicSink) {
+    let mut panic = PANIC.lock().expect("Mutex has been poisoned");
+    panic.sink = sink;
+}
+
+/// Wraps the `sink` function in a struct so that we can implement the `Send`
+/// trait.
+struct Sink {
+    sink: &'static PanicSink,
+}
+
+/// SAFETY: The [`Sink`] is local to this crate and will be enclosed in a
+/// Mutex so is safe to make `Send`.
+unsafe impl Send for Sink {}
+
+/// The sink to use for the `ocall_panic`
+static PANIC: Lazy<Mutex<Sink>> = Lazy::new(|| {
+    Mutex::new(Sink {
+        sink: &default_panic_sink,
+    })
+});
+
+/// A [`PanicSink`] that directs to [`std::io::stderr`]
+fn default_panic_sink(panic: &EnclavePanic) {
+    eprintln!("{panic}");
+}
+
+/// Convert a buffer from the enclave into a string.
+///
+/// # Safety
+/// `ptr` must point to at least `len` bytes.
+unsafe fn lossy_str<'a>(ptr: *const c_char, len: usize) -> Cow<'a, str> {
+    let bytes = slice::from_raw_parts(ptr as *const u8, len);
+    String::from_utf8_lossy(bytes)
+}
+
+#[no_mangle]
+/// The ocall that will take in the panics from the enclave.
+///
+/// # Arguments
+/// * `file` - The source file the panic originated from.
+/// * `file_len` - The byte length of `file`
+/// * `line` - The line in `file` the panic originated from
+/// * `column` - The column in `line` the panic originated from
+/// * `msg` - The panic message.
+/// * `msg_len` - The byte length of `msg`
+extern "C" fn ocall_panic(
+    file: *const c_char,
+    file_len: usize,
+    line: u32,
+    column: u32,
+    msg: *const c_char,
+    msg_len: usize,
+) {
+    // SAFETY: Converting from C interface to Rust. We must rely on the enclave
+    // side of the implementation to provide the correct lengths for the input
+    // buffers
+    let file = unsafe { lossy_str(file, file_len) };
+    let message = unsafe { lossy_str(msg, msg_len) };
+    let panic = PANIC.lock().expect("Mutex has been poisoned");
+    (panic.sink)(&EnclavePanic {
+        file: &file,
+        line,
+        column,
+        message: &message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_sgx_core_sys_types::sgx_status_t;
+    use mc_sgx_urts::EnclaveBuilder;
+    use serial_test::serial;
+    use test_enclave::{ecall_panic, ENCLAVE};
+
+    /// The file, line, column and message of the panics received
+    static TEST_PANICS: Lazy<Mutex<Vec<(String, u32, u32, String)>>> =
+        Lazy::new(|| Mutex::new(Vec::new()));
+    fn test_panic_sink(panic: &EnclavePanic) {
+        let mut panics = TEST_PANICS.lock().expect("Mutex has been poisoned");
+        panics.push((
+            panic.file.to_string(),
+            panic.line,
+            panic.column,
+            panic.message.to_string(),
+        ));
+    }
+
+    fn panic_in_enclave(message: &str) -> Vec<(String, u32, u32, String)> {
+        TEST_PANICS.lock().expect("Mutex has been poisoned").clear();
+        panic_sink(&test_panic_sink);
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = enclave.id();
+
+        let status = unsafe { ecall_panic(*id, message.as_ptr() as *const c_char, message.len()) };
+
+        assert_eq!(status, sgx_status_t::SGX_ERROR_ENCLAVE_CRASHED);
+        TEST_PANICS.lock().expect("Mutex has been poisoned").clone()
+    }
+
+    #[test]
+    #[serial]
+    fn panic_from_enclave_has_message() {
+        let panics = panic_in_enclave("what happened");
+
+        assert_eq!(panics.len(), 1);
+        assert_eq!(panics[0].3, "what happened");
+    }
+
+    #[test]
+    #[serial]
+    fn panic_from_enclave_has_location() {
+        let panics = panic_in_enclave("where did it happen");
+
+        let (file, line, column, _) = &panics[0];
+        assert!(file.ends_with("panic.rs"), "Unexpected file {file}");
+        assert_ne!(*line, 0);
+        assert_ne!(*column, 0);
+    }
+
+    #[test]
+    fn display_format() {
+        let panic = EnclavePanic {
+            file: "src/lib.rs",
+            line: 12,
+            column: 5,
+            message: "oh no",
+        };
+
+        assert_eq!(
+            panic.to_string(),
+            "enclave panicked at 'oh no', src/lib.rs:12:5"
+        );
+    }
+}
@@ -13,10 +13,77 @@
 //! - Removed examples that were not possible in an SGX enclave have been omitted
 //! - Ran `cargo fmt`
 //! - Removed unnecessary unsafe blocks
-//! - Removed timeout since there isn't a secure timer in SGX enclaves
+//! - Timeouts are only available with the `untrusted-time` feature, since
+//!   there isn't a secure timer in SGX enclaves. Time is measured with the
+//!   [`Clock`](crate::time::Clock) provided to
+//!   [`set_clock()`](crate::time::set_clock) instead of `Instant`
 use crate::sys::locks as sys;
 use crate::{mutex, LockResult, MutexGuard, PoisonError};
 use core::fmt;
+#[cfg(feature = "untrusted-time")]
+use {crate::time, core::time::Duration};
+
+/// A type indicating whether a timed wait on a condition variable returned
+/// due to a time out or not.
+///
+/// It is returned by the [`wait_timeout`] method.
+///
+/// The time comes from the host by default, so whether a wait timed out is
+/// only advisory. See [`time`](crate::time) for more information.
+///
+/// [`wait_timeout`]: Condvar::wait_timeout
+#[cfg(feature = "untrusted-time")]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WaitTimeoutResult(pub(crate) bool);
+
+#[cfg(feature = "untrusted-time")]
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait was known to have timed out.
+    ///
+    /// # Examples
+    ///
+    /// The host calls `ecall_wait_for_ready()` and, from another thread,
+    /// `ecall_ready()`, which updates a boolean value and then notifies the
+    /// condvar.
+    ///
+    /// `ecall_wait_for_ready()` waits with a 10 millisecond timeout on the
+    /// condvar and gives up upon timeout.
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use mc_sgx_sync::{Condvar, Mutex};
+    ///
+    /// static READY: Mutex<bool> = Mutex::new(false);
+    /// static CONDVAR: Condvar = Condvar::new();
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn ecall_wait_for_ready() -> bool {
+    ///     let mut ready = READY.lock().unwrap();
+    ///     loop {
+    ///         let result = CONDVAR.wait_timeout(ready, Duration::from_millis(10)).unwrap();
+    ///         // 10 milliseconds have passed, or maybe the value changed!
+    ///         ready = result.0;
+    ///         if *ready {
+    ///             // We received the notification and the value has been updated, we can leave.
+    ///             return true;
+    ///         }
+    ///         if result.1.timed_out() {
+    ///             return false;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn ecall_ready() {
+    ///     *READY.lock().unwrap() = true;
+    ///     CONDVAR.notify_one();
+    /// }
+    /// ```
+    #[must_use]
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
 
 /// A Condition Variable
 ///
@@ -155,6 +222,179 @@ impl Condvar {
         Ok(guard)
     }
 
+    /// Waits on this condition variable for a notification, timing out after a
+    /// specified duration.
+    ///
+    /// The semantics of this function are equivalent to [`wait`] except that
+    /// the thread will be blocked for roughly no longer than `dur`. This
+    /// method should not be used for precise timing due to anomalies such as
+    /// preemption or platform differences that might not cause the maximum
+    /// amount of time waited to be precisely `dur`.
+    ///
+    /// The time is measured with the [`Clock`](time::Clock) provided to
+    /// [`set_clock()`](time::set_clock), which by default comes from the host.
+    /// Untrusted time is **only advisory**, the host can make this return
+    /// early, or never.
+    ///
+    /// Note that the best effort is made to ensure that the time waited is
+    /// measured with a monotonic clock, and not affected by the changes made to
+    /// the system time. This function is susceptible to spurious wakeups.
+    /// Condition variables normally have a boolean predicate associated with
+    /// them, and the predicate must always be checked each time this function
+    /// returns to protect against spurious wakeups. Additionally, it is
+    /// typically desirable for the timeout to not exceed some duration in
+    /// spite of spurious wakes, thus the sleep-duration is decremented by the
+    /// amount slept. Alternatively, use the `wait_timeout_while` method
+    /// to wait with a timeout while a predicate is true.
+    ///
+    /// The returned [`WaitTimeoutResult`] value indicates if the timeout is
+    /// known to have elapsed.
+    ///
+    /// Like [`wait`], the lock specified will be re-acquired when this function
+    /// returns, regardless of whether the timeout elapsed or not.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the mutex being waited on is
+    /// poisoned when this thread re-acquires the lock. For more information,
+    /// see information about [poisoning] on the [`Mutex`] type.
+    ///
+    /// # Panics
+    ///
+    /// This function will [`panic!`] if the ocall to wait on the host fails.
+    ///
+    /// [`wait`]: Self::wait
+    /// [`wait_timeout_while`]: Self::wait_timeout_while
+    /// [poisoning]: super::Mutex#poisoning
+    /// [`Mutex`]: super::Mutex
+    ///
+    /// # Examples
+    ///
+    /// The host calls `ecall_wait_for_ready()` and, from another thread,
+    /// `ecall_ready()`.
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use mc_sgx_sync::{Condvar, Mutex};
+    ///
+    /// static READY: Mutex<bool> = Mutex::new(false);
+    /// static CONDVAR: Condvar = Condvar::new();
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn ecall_wait_for_ready() -> bool {
+    ///     let mut ready = READY.lock().unwrap();
+    ///     // as long as the value inside the `Mutex<bool>` is `false`, and the
+    ///     // timeout hasn't passed, we wait
+    ///     while !*ready {
+    ///         let (guard, result) = CONDVAR.wait_timeout(ready, Duration::from_millis(10)).unwrap();
+    ///         ready = guard;
+    ///         if result.timed_out() {
+    ///             break;
+    ///         }
+    ///     }
+    ///     *ready
+    /// }
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn ecall_ready() {
+    ///     *READY.lock().unwrap() = true;
+    ///     CONDVAR.notify_one();
+    /// }
+    /// ```
+    #[cfg(feature = "untrusted-time")]
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> LockResult<(MutexGuard<'a, T>, WaitTimeoutResult)> {
+        let (poisoned, result) = {
+            let result = wait_timeout(&self.inner, mutex::guard_lock(&guard), dur);
+            (mutex::guard_poison(&guard).get(), result)
+        };
+        if poisoned {
+            Err(PoisonError::new((guard, result)))
+        } else {
+            Ok((guard, result))
+        }
+    }
+
+    /// Waits on this condition variable for a notification, timing out after a
+    /// specified duration.
+    ///
+    /// The semantics of this function are equivalent to [`wait_while`] except
+    /// that the thread will be blocked for roughly no longer than `dur`. This
+    /// method should not be used for precise timing due to anomalies such as
+    /// preemption or platform differences that might not cause the maximum
+    /// amount of time waited to be precisely `dur`.
+    ///
+    /// The time is measured with the [`Clock`](time::Clock) provided to
+    /// [`set_clock()`](time::set_clock), which by default comes from the host.
+    /// Untrusted time is **only advisory**, the host can make this return
+    /// early, or never.
+    ///
+    /// Note that the best effort is made to ensure that the time waited is
+    /// measured with a monotonic clock, and not affected by the changes made to
+    /// the system time.
+    ///
+    /// The returned [`WaitTimeoutResult`] value indicates if the timeout is
+    /// known to have elapsed without the condition being met.
+    ///
+    /// Like [`wait_while`], the lock specified will be re-acquired when this
+    /// function returns, regardless of whether the timeout elapsed or not.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the mutex being waited on is
+    /// poisoned when this thread re-acquires the lock. For more information,
+    /// see information about [poisoning] on the [`Mutex`] type.
+    ///
+    /// # Panics
+    ///
+    /// This function will [`panic!`] if the ocall to wait on the host fails.
+    ///
+    /// [`wait_while`]: Self::wait_while
+    /// [`wait_timeout`]: Self::wait_timeout
+    /// [poisoning]: super::Mutex#poisoning
+    /// [`Mutex`]: super::Mutex
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::time::Duration;
+    /// use mc_sgx_sync::{Condvar, Mutex};
+    ///
+    /// let pair = (Mutex::new(true), Condvar::new());
+    ///
+    /// // wait for the thread to start up
+    /// let (lock, cvar) = &*pair;
+    /// let result = cvar.wait_timeout_while(
+    ///     lock.lock().unwrap(),
+    ///     Duration::from_millis(100),
+    ///     |&mut pending| pending,
+    /// ).unwrap();
+    /// if result.1.timed_out() {
+    ///     // timed-out without the condition ever evaluating to false.
+    /// }
+    /// // access the locked mutex via result.0
+    /// ```
+    #[cfg(feature = "untrusted-time")]
+    pub fn wait_timeout_while<'a, T, F>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+        mut condition: F,
+    ) -> LockResult<(MutexGuard<'a, T>, WaitTimeoutResult)>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        wait_timeout_while(
+            guard,
+            dur,
+            |guard| condition(&mut **guard),
+            |guard, timeout| self.wait_timeout(guard, timeout).map(|(guard, _)| guard),
+        )
+    }
+
     /// Wakes up one blocked thread on this condvar.
     ///
     /// If there is a blocked thread on this condition variable, then it will
@@ -214,6 +454,51 @@ impl Condvar {
     }
 }
 
+/// Wait on `condvar` for a notification, with `lock` held, timing out after
+/// `dur`.
+///
+/// Shared by the poisoning and non-poisoning condition variables.
+#[cfg(feature = "untrusted-time")]
+pub(crate) fn wait_timeout(
+    condvar: &sys::Condvar,
+    lock: &sys::Mutex,
+    dur: Duration,
+) -> WaitTimeoutResult {
+    let deadline = time::now().saturating_add(dur);
+    condvar.wait_timeout(lock, dur);
+    // The clock, not the host's wait, determines if the deadline has passed.
+    WaitTimeoutResult(time::now() >= deadline)
+}
+
+/// Wait, with `wait_timeout`, while `condition` returns `true`, timing out
+/// after `dur` in total.
+///
+/// Shared by the poisoning and non-poisoning condition variables, which
+/// differ in the guards they wait with and in the errors of `wait_timeout`.
+///
+/// # Errors
+/// The first error from `wait_timeout`.
+#[cfg(feature = "untrusted-time")]
+pub(crate) fn wait_timeout_while<G, E>(
+    mut guard: G,
+    dur: Duration,
+    mut condition: impl FnMut(&mut G) -> bool,
+    mut wait_timeout: impl FnMut(G, Duration) -> Result<G, E>,
+) -> Result<(G, WaitTimeoutResult), E> {
+    let start = time::now();
+    loop {
+        if !condition(&mut guard) {
+            return Ok((guard, WaitTimeoutResult(false)));
+        }
+        let elapsed = time::now().saturating_sub(start);
+        let timeout = match dur.checked_sub(elapsed) {
+            Some(timeout) => timeout,
+            None => return Ok((guard, WaitTimeoutResult(true))),
+        };
+        guard = wait_timeout(guard, timeout)?;
+    }
+}
+
 impl fmt::Debug for Condvar {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Condvar").finish_non_exhaustive()
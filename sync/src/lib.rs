@@ -3,14 +3,27 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs, missing_debug_implementations)]
 #![no_std]
-#![feature(error_in_core, must_not_suspend, negative_impls)]
+#![feature(dropck_eyepatch, error_in_core, must_not_suspend, negative_impls)]
 
 mod condvar;
+mod lazy_lock;
 mod mutex;
+pub mod nonpoison;
+mod once;
+mod once_lock;
 mod poison;
+mod remutex;
 pub use condvar::Condvar;
+#[cfg(feature = "untrusted-time")]
+pub use condvar::WaitTimeoutResult;
+pub use lazy_lock::LazyLock;
 pub use mutex::{Mutex, MutexGuard};
+pub use once::{Once, OnceState};
+pub use once_lock::OnceLock;
 pub use poison::{LockResult, PoisonError, TryLockError, TryLockResult};
+pub use remutex::{ReentrantMutex, ReentrantMutexGuard};
 pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 mod rwlock;
 mod sys;
+#[cfg(feature = "untrusted-time")]
+pub mod time;
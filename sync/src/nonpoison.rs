@@ -0,0 +1,47 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Synchronization primitives which do not implement poisoning.
+//!
+//! This mirrors the unstable
+//! [`std::sync::nonpoison`](https://doc.rust-lang.org/nightly/std/sync/nonpoison/index.html)
+//! module.
+//!
+//! The types in this module skip the poison tracking and return their guards
+//! directly, instead of in a [`LockResult`](crate::LockResult). A panic while
+//! holding one of their locks doesn't poison it. With the default panic
+//! strategy of SGX enclaves, abort, the lock is never observed again. When the
+//! enclave unwinds instead, see the `unwind` feature of `mc-sgx-panic`, the
+//! lock is released during unwinding and the next thread to acquire it may
+//! observe data that was left partially updated.
+//!
+//! The primitives share the same backend as the poisoning primitives at the
+//! root of this crate. They can be converted to and from the poisoning
+//! primitives with [`From`]. Converting a poisoned lock ignores the poison.
+
+use core::fmt;
+
+mod condvar;
+mod mutex;
+mod rwlock;
+
+#[cfg(feature = "untrusted-time")]
+pub use crate::WaitTimeoutResult;
+pub use condvar::Condvar;
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A type alias for the result of a nonblocking locking method.
+pub type TryLockResult<Guard> = Result<Guard, WouldBlock>;
+
+/// A lock could not be acquired at this time because the operation would
+/// otherwise block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WouldBlock;
+
+impl fmt::Display for WouldBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "try_lock failed because the operation would block".fmt(f)
+    }
+}
+
+impl core::error::Error for WouldBlock {}
@@ -0,0 +1,154 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! A condition variable for use with the non-poisoning [`Mutex`]
+
+use super::mutex::{self, MutexGuard};
+use crate::sys::locks as sys;
+use core::fmt;
+#[cfg(feature = "untrusted-time")]
+use {crate::condvar, crate::WaitTimeoutResult, core::convert::Infallible, core::time::Duration};
+
+/// A Condition Variable for use with the non-poisoning [`Mutex`].
+///
+/// This is the same as [`crate::Condvar`] except that the guards are returned
+/// directly instead of in a [`LockResult`](crate::LockResult).
+///
+/// [`Mutex`]: super::Mutex
+pub struct Condvar {
+    inner: sys::Condvar,
+}
+
+impl Condvar {
+    /// Creates a new condition variable which is ready to be waited on and
+    /// notified.
+    #[must_use]
+    pub const fn new() -> Condvar {
+        Condvar {
+            inner: sys::Condvar::new(),
+        }
+    }
+
+    /// Blocks the current thread until this condition variable receives a
+    /// notification.
+    ///
+    /// This function will atomically unlock the mutex specified (represented by
+    /// `guard`) and block the current thread. Any calls to [`notify_one`] or
+    /// [`notify_all`] which happen logically after the mutex is unlocked are
+    /// candidates to wake this thread up. When this function call returns, the
+    /// lock specified will have been re-acquired.
+    ///
+    /// Note that this function is susceptible to spurious wakeups.
+    ///
+    /// # Panics
+    ///
+    /// This function may panic if it is used with more than one mutex over
+    /// time.
+    ///
+    /// [`notify_one`]: Self::notify_one
+    /// [`notify_all`]: Self::notify_all
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.inner.wait(mutex::guard_lock(&guard));
+        guard
+    }
+
+    /// Blocks the current thread until the provided condition becomes false.
+    ///
+    /// `condition` is checked immediately; if not met (returns `true`), this
+    /// will [`wait`] for the next notification then check again. This repeats
+    /// until `condition` returns `false`, in which case this function returns.
+    ///
+    /// [`wait`]: Self::wait
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// a specified duration.
+    ///
+    /// The semantics of this function are equivalent to [`wait`] except that
+    /// the thread will be blocked for roughly no longer than `dur`.
+    ///
+    /// The time comes from the host by default, so whether a wait timed out
+    /// is only advisory. See [`time`](crate::time) for more information.
+    ///
+    /// [`wait`]: Self::wait
+    #[cfg(feature = "untrusted-time")]
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let result = condvar::wait_timeout(&self.inner, mutex::guard_lock(&guard), dur);
+        (guard, result)
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// a specified duration.
+    ///
+    /// The semantics of this function are equivalent to [`wait_while`] except
+    /// that the thread will be blocked for roughly no longer than `dur`.
+    ///
+    /// [`wait_while`]: Self::wait_while
+    #[cfg(feature = "untrusted-time")]
+    pub fn wait_timeout_while<'a, T, F>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+        mut condition: F,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        condvar::wait_timeout_while(
+            guard,
+            dur,
+            |guard| condition(&mut **guard),
+            |guard, timeout| Ok::<_, Infallible>(self.wait_timeout(guard, timeout).0),
+        )
+        .unwrap_or_else(|never| match never {})
+    }
+
+    /// Wakes up one blocked thread on this condvar.
+    pub fn notify_one(&self) {
+        self.inner.notify_one()
+    }
+
+    /// Wakes up all blocked threads on this condvar.
+    pub fn notify_all(&self) {
+        self.inner.notify_all()
+    }
+}
+
+impl From<crate::Condvar> for Condvar {
+    fn from(_condvar: crate::Condvar) -> Self {
+        Condvar::new()
+    }
+}
+
+impl From<Condvar> for crate::Condvar {
+    fn from(_condvar: Condvar) -> Self {
+        crate::Condvar::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Condvar {
+        Condvar::new()
+    }
+}
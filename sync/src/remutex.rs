@@ -0,0 +1,219 @@
+// Copyright (c) The Rust Foundation
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! remutex.rs implementation more or less copied from
+//! [rust source](https://github.com/rust-lang/rust.git) at
+//! [606c3907](https://github.com/rust-lang/rust/commit/606c3907251397a42e23d3e60de31be9d32525d5)
+//!
+//! Differences:
+//! - The imports were changed to work with the `mc-sgx` crates.
+//! - The stable attributes have been removed
+//! - The unstable attributes have been removed
+//! - Items that were crate only in `std` were converted to `pub`
+//! - Ran `cargo fmt`
+//! - The owning thread is identified by the SGX thread identity,
+//!   `sgx_thread_self()`, instead of the address of a thread local
+//! - Added a `Debug` implementation
+
+use crate::sys::locks as sys;
+use crate::sys::thread;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::Deref;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+/// A re-entrant mutual exclusion
+///
+/// This mutex will block *other* threads waiting for the lock to become
+/// available. The thread which has already locked the mutex can lock it
+/// multiple times without blocking, preventing a common source of deadlocks.
+///
+/// This is useful when an ecall makes an ocall and the host calls back into
+/// the enclave with a nested ecall. The nested ecall runs on the same TCS, and
+/// thus the same thread, as the outer ecall so it can lock the mutex again.
+///
+/// Since the same thread may hold multiple guards at once, the protected data
+/// is only available through a shared reference. Use a [`Cell`] or
+/// [`RefCell`] to mutate the protected data.
+///
+/// # Examples
+///
+/// ```
+/// use core::cell::Cell;
+/// use mc_sgx_sync::ReentrantMutex;
+///
+/// static COUNT: ReentrantMutex<Cell<usize>> = ReentrantMutex::new(Cell::new(0));
+///
+/// let outer = COUNT.lock();
+/// outer.set(outer.get() + 1);
+/// {
+///     // Does not deadlock, the current thread already holds the lock
+///     let inner = COUNT.lock();
+///     inner.set(inner.get() + 1);
+/// }
+/// assert_eq!(outer.get(), 2);
+/// ```
+///
+/// [`Cell`]: core::cell::Cell
+/// [`RefCell`]: core::cell::RefCell
+pub struct ReentrantMutex<T> {
+    mutex: sys::Mutex,
+    owner: AtomicUsize,
+    lock_count: UnsafeCell<u32>,
+    data: T,
+}
+
+unsafe impl<T: Send> Send for ReentrantMutex<T> {}
+unsafe impl<T: Send> Sync for ReentrantMutex<T> {}
+
+impl<T> UnwindSafe for ReentrantMutex<T> {}
+impl<T> RefUnwindSafe for ReentrantMutex<T> {}
+
+/// An RAII implementation of a "scoped lock" of a mutex. When this structure is
+/// dropped (falls out of scope), the lock will be unlocked.
+///
+/// The data protected by the mutex can be accessed through this guard via its
+/// Deref implementation.
+///
+/// # Mutability
+///
+/// Unlike `MutexGuard`, `ReentrantMutexGuard` does not implement `DerefMut`,
+/// because implementation of the trait would violate Rust’s reference aliasing
+/// rules. Use interior mutability (usually `RefCell`) in order to mutate the
+/// guarded data.
+#[must_use = "if unused the ReentrantMutex will immediately unlock"]
+#[clippy::has_significant_drop]
+pub struct ReentrantMutexGuard<'a, T: 'a> {
+    lock: &'a ReentrantMutex<T>,
+}
+
+impl<T> !Send for ReentrantMutexGuard<'_, T> {}
+
+impl<T> ReentrantMutex<T> {
+    /// Creates a new reentrant mutex in an unlocked state.
+    pub const fn new(t: T) -> ReentrantMutex<T> {
+        ReentrantMutex {
+            mutex: sys::Mutex::new(),
+            owner: AtomicUsize::new(0),
+            lock_count: UnsafeCell::new(0),
+            data: t,
+        }
+    }
+
+    /// Acquires a mutex, blocking the current thread until it is able to do so.
+    ///
+    /// This function will block the caller until it is available to acquire
+    /// the mutex. Upon returning, the thread is the only thread with the mutex
+    /// held. When the thread calling this method already holds the lock, the
+    /// call shall succeed without blocking.
+    ///
+    /// # Panics
+    ///
+    /// If the current thread has locked the mutex more than `u32::MAX` times.
+    pub fn lock(&self) -> ReentrantMutexGuard<'_, T> {
+        let this_thread = thread::current_thread_id();
+        // Safety: We only touch lock_count when we own the lock.
+        unsafe {
+            if self.owner.load(Relaxed) == this_thread {
+                self.increment_lock_count();
+            } else {
+                self.mutex.lock();
+                self.owner.store(this_thread, Relaxed);
+                debug_assert_eq!(*self.lock_count.get(), 0);
+                *self.lock_count.get() = 1;
+            }
+        }
+        ReentrantMutexGuard { lock: self }
+    }
+
+    /// Attempts to acquire this lock.
+    ///
+    /// If the lock could not be acquired at this time, then `None` is returned.
+    /// Otherwise, an RAII guard is returned.
+    ///
+    /// This function does not block.
+    ///
+    /// # Panics
+    ///
+    /// If the current thread has locked the mutex more than `u32::MAX` times.
+    pub fn try_lock(&self) -> Option<ReentrantMutexGuard<'_, T>> {
+        let this_thread = thread::current_thread_id();
+        // Safety: We only touch lock_count when we own the lock.
+        unsafe {
+            if self.owner.load(Relaxed) == this_thread {
+                self.increment_lock_count();
+                Some(ReentrantMutexGuard { lock: self })
+            } else if self.mutex.try_lock() {
+                self.owner.store(this_thread, Relaxed);
+                debug_assert_eq!(*self.lock_count.get(), 0);
+                *self.lock_count.get() = 1;
+                Some(ReentrantMutexGuard { lock: self })
+            } else {
+                None
+            }
+        }
+    }
+
+    unsafe fn increment_lock_count(&self) {
+        *self.lock_count.get() = (*self.lock_count.get())
+            .checked_add(1)
+            .expect("lock count overflow in reentrant mutex");
+    }
+}
+
+impl<T: Default> Default for ReentrantMutex<T> {
+    /// Creates a `ReentrantMutex<T>`, with the `Default` value for T.
+    fn default() -> ReentrantMutex<T> {
+        ReentrantMutex::new(Default::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ReentrantMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("ReentrantMutex");
+        match self.try_lock() {
+            Some(guard) => {
+                d.field("data", &&*guard);
+            }
+            None => {
+                struct LockedPlaceholder;
+                impl fmt::Debug for LockedPlaceholder {
+                    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str("<locked>")
+                    }
+                }
+                d.field("data", &LockedPlaceholder);
+            }
+        }
+        d.finish_non_exhaustive()
+    }
+}
+
+impl<T> Deref for ReentrantMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.lock.data
+    }
+}
+
+impl<T> Drop for ReentrantMutexGuard<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // Safety: We own the lock.
+        unsafe {
+            *self.lock.lock_count.get() -= 1;
+            if *self.lock.lock_count.get() == 0 {
+                self.lock.owner.store(0, Relaxed);
+                self.lock.mutex.unlock();
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ReentrantMutexGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
@@ -10,3 +10,5 @@
 //! [`::std`](https://github.com/rust-lang/rust/tree/master/library/std/src)
 
 pub(crate) mod locks;
+pub(crate) mod once;
+pub(crate) mod thread;
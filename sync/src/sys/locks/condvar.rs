@@ -1,19 +1,48 @@
 // Copyright (c) 2023 The MobileCoin Foundation
 
 //! Rust condition variable implementation used in SGX environments
+//!
+//! The SGX condition variable doesn't support timeouts. When the
+//! `untrusted-time` feature is enabled, timed waits block on the host instead.
+//! The waiting thread gives the host the current notification sequence and the
+//! timeout. The host returns when it's told of a newer sequence, by a notify,
+//! or when the timeout expires. This is similar to a futex where the host owns
+//! the wait queue.
 use crate::sys::locks::Mutex;
+#[cfg(feature = "untrusted-time")]
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
+};
+#[cfg(feature = "untrusted-time")]
+use mc_sgx_core_sys_types::sgx_status_t;
 use mc_sgx_tstdc::Condvar as SgxCondvar;
+#[cfg(feature = "untrusted-time")]
+use mc_sgx_util::ResultInto;
 
 /// The condition variable backend to use with the common Rust std lib Condvar
 /// interface
 pub(crate) struct Condvar {
     inner: SgxCondvar,
+    /// Incremented on every notification so that the host can tell if a
+    /// notification happened after a timed waiter started waiting.
+    #[cfg(feature = "untrusted-time")]
+    sequence: AtomicU32,
+    /// The number of threads in a timed wait. Used to avoid notifying the host
+    /// when no one is waiting on it.
+    #[cfg(feature = "untrusted-time")]
+    timed_waiters: AtomicUsize,
 }
 
 impl Condvar {
     pub const fn new() -> Self {
         Self {
             inner: SgxCondvar::new(),
+            #[cfg(feature = "untrusted-time")]
+            sequence: AtomicU32::new(0),
+            #[cfg(feature = "untrusted-time")]
+            timed_waiters: AtomicUsize::new(0),
         }
     }
 
@@ -42,6 +71,8 @@ impl Condvar {
         self.inner
             .notify_one()
             .expect("Condition variable is in an invalid state");
+        #[cfg(feature = "untrusted-time")]
+        self.notify_timed_waiters();
     }
 
     /// Notify *all* waiting threads of the condition variable event
@@ -59,5 +90,99 @@ impl Condvar {
         self.inner
             .notify_all()
             .expect("Condition variable is in an invalid state");
+        #[cfg(feature = "untrusted-time")]
+        self.notify_timed_waiters();
     }
+
+    /// Wait on the condition variable for at most `timeout`
+    ///
+    /// The timeout is measured by the host, so it is only advisory. The host
+    /// may return early, or never. Whether the host reports the `timeout` as
+    /// expired is ignored, callers must check their own deadline against the
+    /// clock.
+    ///
+    /// # Arguments
+    /// * `mutex` - The mutex to paired with the current [`Condvar`]
+    /// * `timeout` - The maximum amount of time to wait
+    ///
+    /// # Panics
+    /// If:
+    /// - the ocall to the host failed
+    /// - the [`Mutex`] is not locked by the current thread
+    #[cfg(feature = "untrusted-time")]
+    pub fn wait_timeout(&self, mutex: &Mutex, timeout: Duration) {
+        self.timed_waiters.fetch_add(1, Ordering::SeqCst);
+        let sequence = self.sequence.load(Ordering::SeqCst);
+        let timeout_nanos = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+
+        mutex.unlock();
+        // Not trusted, the caller's deadline decides if the wait timed out
+        let mut timed_out = 0;
+        let result = unsafe {
+            ocall_condvar_wait_timeout(&mut timed_out, self.key(), sequence, timeout_nanos)
+        }
+        .into_result();
+        mutex.lock();
+
+        self.timed_waiters.fetch_sub(1, Ordering::SeqCst);
+        result.expect("Failed to wait on the host");
+    }
+
+    /// Let the host know of a new notification so that it can wake up any
+    /// timed waiters.
+    ///
+    /// The host wakes up *all* timed waiters of this condition variable, even
+    /// for [`Condvar::notify_one()`]. This is allowed as the waiters must
+    /// already handle spurious wake ups.
+    ///
+    /// # Panics
+    /// If the ocall to the host failed
+    #[cfg(feature = "untrusted-time")]
+    fn notify_timed_waiters(&self) {
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
+        if self.timed_waiters.load(Ordering::SeqCst) != 0 {
+            unsafe { ocall_condvar_wake(self.key(), sequence) }
+                .into_result()
+                .expect("Failed to notify the host");
+        }
+    }
+
+    /// The key the host uses to identify this condition variable
+    #[cfg(feature = "untrusted-time")]
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+#[cfg(feature = "untrusted-time")]
+extern "C" {
+    /// The ocall to wait on the host until notified or the timeout expires
+    ///
+    /// # Arguments
+    /// * `retval` - Set to non zero when the timeout expired prior to a
+    ///   notification.
+    /// * `key` - Identifies the condition variable being waited on.
+    /// * `sequence` - The notification sequence of the condition variable at
+    ///   the start of the wait. The host returns once it has been told of a
+    ///   newer sequence by [`ocall_condvar_wake()`].
+    /// * `timeout_nanos` - The maximum time to wait, in nanoseconds.
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the ocall was successful.
+    fn ocall_condvar_wait_timeout(
+        retval: *mut c_int,
+        key: usize,
+        sequence: u32,
+        timeout_nanos: u64,
+    ) -> sgx_status_t;
+
+    /// The ocall to wake up any host waiters of a condition variable
+    ///
+    /// # Arguments
+    /// * `key` - Identifies the condition variable that was notified.
+    /// * `sequence` - The new notification sequence of the condition variable.
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the ocall was successful.
+    fn ocall_condvar_wake(key: usize, sequence: u32) -> sgx_status_t;
 }
@@ -63,14 +63,14 @@ impl RwLock {
     ///   lock
     pub fn write(&self) {
         self.inner
-            .read()
+            .write()
             .expect("RwLock got into an invalid state.")
     }
 
-    /// Try to acquire a read lock on the [`RwLock`]
+    /// Try to acquire the write lock on the [`RwLock`]
     ///
     /// # Returns
-    /// `true` if a read lock was acquired, `false` otherwise.
+    /// `true` if the write lock was acquired, `false` otherwise.
     pub fn try_write(&self) -> bool {
         self.inner
             .try_write()
@@ -0,0 +1,167 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Rust once implementation used in SGX environments
+//!
+//! The SGX SDK doesn't provide a once primitive, so this is built on top of the
+//! [`Mutex`] and [`Condvar`] backends. The state of the [`Once`] is kept in an
+//! atomic so that the common case of an already completed [`Once`] doesn't
+//! need to take the lock. Threads that find the [`Once`] running wait on the
+//! [`Condvar`] until the running thread finishes.
+//!
+//! The states and their transitions follow the
+//! [futex once](https://github.com/rust-lang/rust/blob/master/library/std/src/sys_common/once/futex.rs)
+//! implementation in Rust's std.
+
+use crate::once::OnceState as PublicOnceState;
+use crate::sys::locks::{Condvar, Mutex};
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// No initialization has run yet, and no thread is currently running one.
+const INCOMPLETE: u8 = 0;
+/// Some thread previously attempted to initialize the [`Once`], but it
+/// panicked, so the [`Once`] is now poisoned. There are no other threads
+/// currently accessing this [`Once`].
+const POISONED: u8 = 1;
+/// Some thread is currently attempting to run initialization. It may succeed,
+/// so all future threads need to wait for it to finish.
+const RUNNING: u8 = 2;
+/// Initialization has completed and all future calls should finish
+/// immediately.
+const COMPLETE: u8 = 3;
+
+/// The state passed to the initialization closure of [`Once`]
+pub(crate) struct OnceState {
+    poisoned: bool,
+    set_state_to: Cell<u8>,
+}
+
+impl OnceState {
+    /// Returns `true` if the associated [`Once`] was poisoned prior to the
+    /// invocation of the closure passed to [`Once::call`].
+    #[inline]
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Poison the associated [`Once`] without explicitly panicking.
+    #[inline]
+    pub(crate) fn poison(&self) {
+        self.set_state_to.set(POISONED);
+    }
+}
+
+/// Sets the state of the [`Once`] when dropped and wakes up any waiting
+/// threads.
+///
+/// If the initialization closure panics this will be dropped with the initial
+/// `POISONED` value, marking the [`Once`] as poisoned.
+struct CompletionGuard<'a> {
+    once: &'a Once,
+    set_state_on_drop_to: u8,
+}
+
+impl<'a> Drop for CompletionGuard<'a> {
+    fn drop(&mut self) {
+        // The state is updated while holding the lock so that waiting threads
+        // can't miss the notification between checking the state and waiting
+        // on the condition variable.
+        self.once.mutex.lock();
+        self.once
+            .state
+            .store(self.set_state_on_drop_to, Ordering::Release);
+        self.once.mutex.unlock();
+        self.once.condvar.notify_all();
+    }
+}
+
+/// The once backend to use with the common Rust std lib Once interface
+pub(crate) struct Once {
+    state: AtomicU8,
+    mutex: Mutex,
+    condvar: Condvar,
+}
+
+impl Once {
+    /// Create a new [`Once`]
+    #[inline]
+    pub(crate) const fn new() -> Once {
+        Once {
+            state: AtomicU8::new(INCOMPLETE),
+            mutex: Mutex::new(),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Returns `true` if initialization has completed
+    #[inline]
+    pub(crate) fn is_completed(&self) -> bool {
+        // Use acquire ordering to make all initialization changes visible to
+        // the current thread.
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// Run the initialization function `f` if initialization hasn't already
+    /// completed.
+    ///
+    /// If another thread is currently running the initialization this will
+    /// block until that thread finishes.
+    ///
+    /// # Arguments
+    /// * `ignore_poisoning` - When `true` a previously poisoned [`Once`] will
+    ///   rerun `f`.
+    /// * `f` - The initialization function
+    ///
+    /// # Panics
+    /// If the [`Once`] has been poisoned and `ignore_poisoning` is `false`.
+    #[cold]
+    #[track_caller]
+    pub(crate) fn call(&self, ignore_poisoning: bool, f: &mut dyn FnMut(&PublicOnceState)) {
+        let mut state = self.state.load(Ordering::Acquire);
+        loop {
+            match state {
+                POISONED if !ignore_poisoning => {
+                    // Panic to propagate the poison.
+                    panic!("Once instance has previously been poisoned");
+                }
+                INCOMPLETE | POISONED => {
+                    // Try to register the current thread as the one running.
+                    if let Err(new) = self.state.compare_exchange_weak(
+                        state,
+                        RUNNING,
+                        Ordering::Acquire,
+                        Ordering::Acquire,
+                    ) {
+                        state = new;
+                        continue;
+                    }
+                    // `guard` will set the new state on drop.
+                    let mut guard = CompletionGuard {
+                        once: self,
+                        set_state_on_drop_to: POISONED,
+                    };
+                    // Run the function, letting it know if we're poisoned or not.
+                    let f_state = PublicOnceState {
+                        inner: OnceState {
+                            poisoned: state == POISONED,
+                            set_state_to: Cell::new(COMPLETE),
+                        },
+                    };
+                    f(&f_state);
+                    guard.set_state_on_drop_to = f_state.inner.set_state_to.get();
+                    return;
+                }
+                RUNNING => {
+                    self.mutex.lock();
+                    while self.state.load(Ordering::Acquire) == RUNNING {
+                        self.condvar.wait(&self.mutex);
+                    }
+                    self.mutex.unlock();
+                    state = self.state.load(Ordering::Acquire);
+                }
+                COMPLETE => return,
+                _ => unreachable!("state is never set to invalid values"),
+            }
+        }
+    }
+}
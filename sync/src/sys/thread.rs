@@ -0,0 +1,23 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Platform specific thread identity.
+//!
+//! [mc-sgx-tstdc](https://docs.rs/mc-sgx-tstdc/latest/mc_sgx_tstdc/) doesn't
+//! provide a wrapper for the thread identity so this goes directly to
+//! [mc-sgx-tstdc-sys](https://docs.rs/mc-sgx-tstdc-sys/latest/mc_sgx_tstdc_sys/).
+
+use mc_sgx_tstdc_sys::sgx_thread_self;
+
+/// Get a unique identifier for the current thread.
+///
+/// This is the SGX thread identity, `sgx_thread_self()`, which is unique per
+/// TCS for the lifetime of the enclave. It will never be `0`, so `0` can be
+/// used to indicate no thread.
+///
+/// Note that nested ecalls, an ecall made while handling an ocall, execute on
+/// the same TCS as the outer ecall. They will have the same identifier.
+pub(crate) fn current_thread_id() -> usize {
+    // SAFETY: `sgx_thread_self()` has no preconditions, it only reads the
+    // thread data of the current TCS.
+    unsafe { sgx_thread_self() }
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Time sources for the timed waits of [`Condvar`](crate::Condvar).
+//!
+//! SGX enclaves do not have a trusted source of time. The default [`Clock`],
+//! [`UntrustedClock`], asks the host for the current time. The host is free to
+//! report any time it wants, so timeouts are **only advisory**. A malicious
+//! host can make timed waits return early, or never. Timeouts must not be
+//! relied upon for the correctness or security of enclave code.
+//!
+//! A different [`Clock`] can be provided with [`set_clock()`].
+
+use crate::RwLock;
+use core::time::Duration;
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_util::ResultInto;
+
+/// A source of time used to compute the deadlines of timed waits.
+pub trait Clock {
+    /// The current time.
+    ///
+    /// This is measured from an arbitrary, but fixed, point in time. It should
+    /// be monotonic.
+    fn now(&self) -> Duration;
+}
+
+/// A [`Clock`] whose time comes from the host, through the
+/// `ocall_untrusted_clock_now()` ocall.
+///
+/// The time from this clock is **untrusted**, it is only advisory.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct UntrustedClock;
+
+impl Clock for UntrustedClock {
+    /// The current time as reported by the host.
+    ///
+    /// # Panics
+    /// If the ocall to the host fails.
+    fn now(&self) -> Duration {
+        let mut nanos = 0;
+        unsafe { ocall_untrusted_clock_now(&mut nanos) }
+            .into_result()
+            .expect("Failed to get the untrusted time from the host");
+        Duration::from_nanos(nanos)
+    }
+}
+
+/// The clock to use for timed waits
+static CLOCK: RwLock<&'static (dyn Clock + Sync)> = RwLock::new(&UntrustedClock);
+
+/// Specify the [`Clock`] to use for timed waits.
+///
+/// By default [`UntrustedClock`] is used.
+///
+/// # Arguments
+/// * `clock` - The clock to use for all subsequent timed waits
+pub fn set_clock(clock: &'static (dyn Clock + Sync)) {
+    let mut current = CLOCK.write().expect("RwLock has been poisoned");
+    *current = clock;
+}
+
+/// The current time from the [`Clock`] provided to [`set_clock()`].
+pub(crate) fn now() -> Duration {
+    let clock = CLOCK.read().expect("RwLock has been poisoned");
+    clock.now()
+}
+
+extern "C" {
+    /// The ocall to get the current time from the host
+    ///
+    /// # Arguments
+    /// * `retval` - The current time, in nanoseconds, from an arbitrary, but
+    ///   fixed, point in time.
+    ///
+    /// # Returns
+    /// `sgx_status_t::SGX_SUCCESS` when the ocall was successful.
+    fn ocall_untrusted_clock_now(retval: *mut u64) -> sgx_status_t;
+}
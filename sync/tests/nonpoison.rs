@@ -0,0 +1,137 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Tests for the `mc_sgx_sync::nonpoison` primitives running in an enclave
+//! with multiple TCSs.
+//!
+//! The timed waits use the default ocalls of the test enclave, whose waits
+//! sleep for at most a millisecond. The enclave keeps re-checking the
+//! condition against the host's monotonic clock until it's met or the timeout
+//! expires.
+
+use mc_sgx_urts::{Enclave, EnclaveBuilder};
+use mc_sgx_util::ResultInto;
+use std::thread;
+use std::time::{Duration, Instant};
+use test_enclave::{
+    ecall_nonpoison_condvar_notify, ecall_nonpoison_condvar_wait_timeout,
+    ecall_nonpoison_mutex_hold, ecall_nonpoison_mutex_increment, ecall_nonpoison_mutex_release,
+    ecall_nonpoison_mutex_try_lock, ecall_nonpoison_mutex_value, ENCLAVE,
+};
+
+/// The number of host threads to call into the enclave with.
+///
+/// Must be less than the `TCSNum` in the test enclave's `config.xml`.
+const THREADS: usize = 4;
+
+/// The number of times each thread will call the ecall.
+const ITERATIONS: usize = 50;
+
+/// The number of iterations to hold the lock for in each ecall.
+const SPINS: usize = 1000;
+
+/// The maximum number of attempts to observe the lock being held by another
+/// TCS.
+const ATTEMPTS: usize = 1_000_000;
+
+fn increment(enclave: &Enclave) {
+    unsafe { ecall_nonpoison_mutex_increment(*enclave.id(), SPINS) }
+        .into_result()
+        .expect("Ecall failed");
+}
+
+fn value(enclave: &Enclave) -> usize {
+    let mut value = 0;
+    unsafe { ecall_nonpoison_mutex_value(*enclave.id(), &mut value) }
+        .into_result()
+        .expect("Ecall failed");
+    value
+}
+
+fn try_lock(enclave: &Enclave) -> bool {
+    let mut locked = 0;
+    unsafe { ecall_nonpoison_mutex_try_lock(*enclave.id(), &mut locked) }
+        .into_result()
+        .expect("Ecall failed");
+    locked != 0
+}
+
+/// Wait on the condition variable for at most `timeout`.
+///
+/// # Returns
+/// Whether the wait timed out.
+fn wait_timeout(enclave: &Enclave, timeout: Duration) -> bool {
+    let mut timed_out = 0;
+    let millis = u64::try_from(timeout.as_millis()).expect("Timeout too long");
+    unsafe { ecall_nonpoison_condvar_wait_timeout(*enclave.id(), millis, &mut timed_out) }
+        .into_result()
+        .expect("Ecall failed");
+    timed_out != 0
+}
+
+#[test]
+fn concurrent_lockers_are_exclusive() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| (0..ITERATIONS).for_each(|_| increment(&enclave)));
+        }
+    });
+
+    assert_eq!(value(&enclave), THREADS * ITERATIONS);
+}
+
+#[test]
+fn try_lock_fails_while_another_tcs_holds_the_lock() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    thread::scope(|scope| {
+        let holder = scope.spawn(|| {
+            unsafe { ecall_nonpoison_mutex_hold(*enclave.id()) }
+                .into_result()
+                .expect("Ecall failed");
+        });
+
+        // The holder may not have locked the mutex yet
+        let blocked = (0..ATTEMPTS).any(|_| !try_lock(&enclave));
+
+        unsafe { ecall_nonpoison_mutex_release(*enclave.id()) }
+            .into_result()
+            .expect("Ecall failed");
+        holder.join().expect("Holder thread panicked");
+        assert!(blocked);
+    });
+
+    assert!(try_lock(&enclave));
+}
+
+#[test]
+fn wait_times_out_without_notification() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+    let timeout = Duration::from_millis(50);
+
+    let start = Instant::now();
+    let timed_out = wait_timeout(&enclave, timeout);
+
+    assert!(timed_out);
+    assert!(start.elapsed() >= timeout);
+}
+
+#[test]
+fn notification_from_another_tcs_wakes_up_waiter() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+    let timeout = Duration::from_secs(60);
+
+    let start = Instant::now();
+    let timed_out = thread::scope(|scope| {
+        let waiter = scope.spawn(|| wait_timeout(&enclave, timeout));
+        thread::sleep(Duration::from_millis(50));
+        unsafe { ecall_nonpoison_condvar_notify(*enclave.id()) }
+            .into_result()
+            .expect("Ecall failed");
+        waiter.join().expect("Waiter thread panicked")
+    });
+
+    assert!(!timed_out);
+    assert!(start.elapsed() < timeout);
+}
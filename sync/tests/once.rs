@@ -0,0 +1,146 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Tests for `mc_sgx_sync::Once` and `mc_sgx_sync::LazyLock` running in an
+//! enclave with multiple TCSs.
+//!
+//! The poisoning tests use the unwind enclave, so that the panics which poison
+//! the `Once` can be caught.
+
+use mc_sgx_urts::{Enclave, EnclaveBuilder};
+use mc_sgx_util::ResultInto;
+use std::sync::Barrier;
+use std::thread;
+use test_enclave::{
+    ecall_lazy_lock_force, ecall_once_call_once, ecall_once_call_once_force, ecall_once_poison,
+    ENCLAVE, ENCLAVE_UNWIND,
+};
+
+/// The number of host threads to call into the enclave with.
+///
+/// Must be less than the `TCSNum` in the test enclave's `config.xml`.
+const THREADS: usize = 4;
+
+/// The number of iterations the initialization spins for, so that the other
+/// threads find it running.
+const SPINS: usize = 100_000;
+
+/// Run `ecall` once on each of [`THREADS`] host threads, released at the same
+/// time.
+///
+/// # Returns
+/// The result of each thread's `ecall`.
+fn race<T: Send>(enclave: &Enclave, ecall: fn(&Enclave) -> T) -> Vec<T> {
+    let barrier = Barrier::new(THREADS);
+    thread::scope(|scope| {
+        let handles = (0..THREADS)
+            .map(|_| {
+                scope.spawn(|| {
+                    barrier.wait();
+                    ecall(enclave)
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Thread panicked"))
+            .collect()
+    })
+}
+
+fn call_once(enclave: &Enclave) -> usize {
+    let mut calls = 0;
+    unsafe { ecall_once_call_once(*enclave.id(), SPINS, &mut calls) }
+        .into_result()
+        .expect("Ecall failed");
+    calls
+}
+
+fn lazy_lock_force(enclave: &Enclave) -> (usize, usize) {
+    let mut value = 0;
+    let mut inits = 0;
+    unsafe { ecall_lazy_lock_force(*enclave.id(), SPINS, &mut value, &mut inits) }
+        .into_result()
+        .expect("Ecall failed");
+    (value, inits)
+}
+
+/// Call `call_once()` on the poisoning `Once`
+///
+/// # Returns
+/// Whether `call_once()` panicked and whether the `Once` is completed.
+fn once_poison(enclave: &Enclave, should_panic: bool) -> (bool, bool) {
+    let mut panicked = 0;
+    let mut completed = 0;
+    unsafe {
+        ecall_once_poison(
+            *enclave.id(),
+            should_panic.into(),
+            &mut panicked,
+            &mut completed,
+        )
+    }
+    .into_result()
+    .expect("Ecall failed");
+    (panicked != 0, completed != 0)
+}
+
+/// Call `call_once_force()` on the poisoning `Once`
+///
+/// # Returns
+/// `None` if the closure wasn't called, otherwise whether the closure was told
+/// the `Once` was poisoned.
+fn once_call_once_force(enclave: &Enclave) -> Option<bool> {
+    let mut ran = 0;
+    let mut poisoned = 0;
+    unsafe { ecall_once_call_once_force(*enclave.id(), &mut ran, &mut poisoned) }
+        .into_result()
+        .expect("Ecall failed");
+    (ran != 0).then_some(poisoned != 0)
+}
+
+#[test]
+fn racing_call_once_calls_closure_once() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    let calls = race(&enclave, call_once);
+
+    assert_eq!(calls, vec![1; THREADS]);
+    assert_eq!(call_once(&enclave), 1);
+}
+
+#[test]
+fn racing_lazy_lock_initializes_once() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    let forced = race(&enclave, lazy_lock_force);
+
+    assert_eq!(forced, vec![(1, 1); THREADS]);
+    assert_eq!(lazy_lock_force(&enclave), (1, 1));
+}
+
+#[test]
+fn panic_poisons_once() {
+    let enclave = EnclaveBuilder::from(ENCLAVE_UNWIND).create().unwrap();
+
+    assert_eq!(once_poison(&enclave, true), (true, false));
+    // The poison propagates to later calls, even when they wouldn't panic
+    assert_eq!(once_poison(&enclave, false), (true, false));
+}
+
+#[test]
+fn call_once_force_clears_poison() {
+    let enclave = EnclaveBuilder::from(ENCLAVE_UNWIND).create().unwrap();
+    once_poison(&enclave, true);
+
+    assert_eq!(once_call_once_force(&enclave), Some(true));
+    assert_eq!(once_poison(&enclave, false), (false, true));
+    assert_eq!(once_call_once_force(&enclave), None);
+}
+
+#[test]
+fn call_once_force_without_poison() {
+    let enclave = EnclaveBuilder::from(ENCLAVE_UNWIND).create().unwrap();
+
+    assert_eq!(once_call_once_force(&enclave), Some(false));
+    assert_eq!(once_poison(&enclave, true), (false, true));
+}
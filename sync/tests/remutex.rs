@@ -0,0 +1,81 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Tests for `mc_sgx_sync::ReentrantMutex` running in an enclave.
+//!
+//! The enclave re-enters itself through `ocall_reenter()`, so the nested
+//! ecalls run on the same TCS as the ecall which already holds the lock.
+
+use mc_sgx_urts::{Enclave, EnclaveBuilder};
+use mc_sgx_util::ResultInto;
+use serial_test::serial;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use test_enclave::{ecall_reentrant_lock, ENCLAVE};
+
+/// The enclave that `ocall_reenter()` should call back into.
+static ENCLAVE_ID: AtomicU64 = AtomicU64::new(0);
+
+#[no_mangle]
+extern "C" fn ocall_reenter(depth: usize) {
+    let id = ENCLAVE_ID.load(Ordering::SeqCst);
+    let mut count = 0;
+    unsafe { ecall_reentrant_lock(id, depth, &mut count) }
+        .into_result()
+        .expect("Nested ecall failed");
+}
+
+fn create_enclave() -> Enclave {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+    ENCLAVE_ID.store(*enclave.id(), Ordering::SeqCst);
+    enclave
+}
+
+fn reentrant_lock(enclave: &Enclave, depth: usize) -> usize {
+    let mut count = 0;
+    unsafe { ecall_reentrant_lock(*enclave.id(), depth, &mut count) }
+        .into_result()
+        .expect("Ecall failed");
+    count
+}
+
+#[test]
+#[serial]
+fn lock_without_reentering() {
+    let enclave = create_enclave();
+
+    assert_eq!(reentrant_lock(&enclave, 1), 1);
+    assert_eq!(reentrant_lock(&enclave, 1), 2);
+}
+
+#[test]
+#[serial]
+fn nested_ecalls_reenter_the_lock() {
+    let enclave = create_enclave();
+
+    assert_eq!(reentrant_lock(&enclave, 3), 3);
+    assert_eq!(reentrant_lock(&enclave, 5), 8);
+}
+
+#[test]
+#[serial]
+fn nested_ecalls_on_multiple_threads() {
+    let threads = 4;
+    let iterations = 25;
+    let depth = 3;
+    let enclave = create_enclave();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for _ in 0..iterations {
+                    reentrant_lock(&enclave, depth);
+                }
+            });
+        }
+    });
+
+    assert_eq!(
+        reentrant_lock(&enclave, 1),
+        threads * iterations * depth + 1
+    );
+}
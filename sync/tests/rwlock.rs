@@ -0,0 +1,100 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Tests for `mc_sgx_sync::RwLock` running in an enclave with multiple TCSs.
+//!
+//! Each host thread calls into the enclave on its own TCS, so the readers and
+//! writers contend on the same `RwLock` from different enclave threads.
+
+use mc_sgx_urts::{Enclave, EnclaveBuilder};
+use mc_sgx_util::ResultInto;
+use std::thread;
+use test_enclave::{ecall_rwlock_read, ecall_rwlock_value, ecall_rwlock_write, ENCLAVE};
+
+/// The number of host threads to call into the enclave with.
+///
+/// Must be less than the `TCSNum` in the test enclave's `config.xml`.
+const THREADS: usize = 4;
+
+/// The number of times each thread will call the ecall.
+const ITERATIONS: usize = 50;
+
+/// The number of iterations to hold the lock for in each ecall.
+const SPINS: usize = 1000;
+
+fn read(enclave: &Enclave) -> usize {
+    let mut violations = 0;
+    unsafe { ecall_rwlock_read(*enclave.id(), SPINS, &mut violations) }
+        .into_result()
+        .expect("Ecall failed");
+    violations
+}
+
+fn write(enclave: &Enclave) -> usize {
+    let mut violations = 0;
+    unsafe { ecall_rwlock_write(*enclave.id(), SPINS, &mut violations) }
+        .into_result()
+        .expect("Ecall failed");
+    violations
+}
+
+fn value(enclave: &Enclave) -> usize {
+    let mut value = 0;
+    unsafe { ecall_rwlock_value(*enclave.id(), &mut value) }
+        .into_result()
+        .expect("Ecall failed");
+    value
+}
+
+/// Run `ecall` [`ITERATIONS`] times on each of `threads` host threads.
+///
+/// # Returns
+/// The total number of violations reported by the ecalls.
+fn run_concurrently(enclave: &Enclave, threads: usize, ecall: fn(&Enclave) -> usize) -> usize {
+    thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|_| scope.spawn(move || (0..ITERATIONS).map(|_| ecall(enclave)).sum::<usize>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Thread panicked"))
+            .sum()
+    })
+}
+
+#[test]
+fn concurrent_writers_are_exclusive() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    let violations = run_concurrently(&enclave, THREADS, write);
+
+    assert_eq!(violations, 0);
+    assert_eq!(value(&enclave), THREADS * ITERATIONS);
+}
+
+#[test]
+fn concurrent_readers_and_writers_are_exclusive() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    let (read_violations, write_violations) = thread::scope(|scope| {
+        let readers = scope.spawn(|| run_concurrently(&enclave, THREADS / 2, read));
+        let writers = scope.spawn(|| run_concurrently(&enclave, THREADS / 2, write));
+        (
+            readers.join().expect("Reader threads panicked"),
+            writers.join().expect("Writer threads panicked"),
+        )
+    });
+
+    assert_eq!(read_violations, 0);
+    assert_eq!(write_violations, 0);
+    assert_eq!(value(&enclave), (THREADS / 2) * ITERATIONS);
+}
+
+#[test]
+fn concurrent_readers_succeed() {
+    let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+
+    let violations = run_concurrently(&enclave, THREADS, read);
+
+    assert_eq!(violations, 0);
+    assert_eq!(value(&enclave), 0);
+}
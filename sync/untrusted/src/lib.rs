@@ -0,0 +1,138 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+#![doc = include_str!("../README.md")]
+#![deny(missing_docs, missing_debug_implementations)]
+
+//! Module for providing the untrusted time and timed waits to an enclave.
+//!
+//! The enclave's `Condvar` can't wait with a timeout on its own. Instead, the
+//! enclave waits on the host via `ocall_condvar_wait_timeout()`. When the
+//! enclave's `Condvar` is notified it tells the host, via
+//! `ocall_condvar_wake()`, so that the host can wake up any waiters.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::ffi::c_int;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// The point in time that the untrusted time is measured from.
+static EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// The latest notification sequence for each enclave condition variable that
+/// has been woken.
+static SEQUENCES: Lazy<Mutex<HashMap<usize, u32>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Signaled whenever an entry in [`SEQUENCES`] changes.
+static WAKE: Condvar = Condvar::new();
+
+/// Is `latest` a newer sequence than `expected`?
+///
+/// The sequences wrap around, so any `latest` within half of the range after
+/// `expected` is considered newer.
+fn is_newer(latest: u32, expected: u32) -> bool {
+    (latest.wrapping_sub(expected) as i32) > 0
+}
+
+#[no_mangle]
+/// The ocall that provides the current untrusted time to the enclave.
+///
+/// # Returns
+/// The number of nanoseconds since the first call to this function.
+extern "C" fn ocall_untrusted_clock_now() -> u64 {
+    u64::try_from(EPOCH.elapsed().as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[no_mangle]
+/// The ocall that waits for an enclave condition variable to be woken.
+///
+/// # Arguments
+/// * `key` - Identifies the enclave condition variable.
+/// * `sequence` - The notification sequence of the condition variable at the
+///   start of the wait.
+/// * `timeout_nanos` - The maximum time to wait, in nanoseconds.
+///
+/// # Returns
+/// Non zero if `timeout_nanos` expired prior to the condition variable being
+/// woken with a sequence newer than `sequence`.
+extern "C" fn ocall_condvar_wait_timeout(key: usize, sequence: u32, timeout_nanos: u64) -> c_int {
+    let timeout = Duration::from_nanos(timeout_nanos);
+    let sequences = SEQUENCES.lock().expect("Mutex has been poisoned");
+    let (_sequences, result) = WAKE
+        .wait_timeout_while(sequences, timeout, |sequences| {
+            !sequences
+                .get(&key)
+                .map_or(false, |latest| is_newer(*latest, sequence))
+        })
+        .expect("Mutex has been poisoned");
+    result.timed_out().into()
+}
+
+#[no_mangle]
+/// The ocall that wakes up the waiters of an enclave condition variable.
+///
+/// # Arguments
+/// * `key` - Identifies the enclave condition variable.
+/// * `sequence` - The new notification sequence of the condition variable.
+extern "C" fn ocall_condvar_wake(key: usize, sequence: u32) {
+    let mut sequences = SEQUENCES.lock().expect("Mutex has been poisoned");
+    sequences.insert(key, sequence);
+    WAKE.notify_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mc_sgx_urts::EnclaveBuilder;
+    use mc_sgx_util::ResultInto;
+    use serial_test::serial;
+    use std::thread;
+    use test_enclave::{ecall_condvar_notify, ecall_condvar_wait_timeout, ENCLAVE};
+
+    #[test]
+    fn newer_sequences() {
+        assert!(is_newer(1, 0));
+        assert!(is_newer(0, u32::MAX));
+        assert!(!is_newer(0, 0));
+        assert!(!is_newer(0, 1));
+        assert!(!is_newer(u32::MAX, 0));
+    }
+
+    #[test]
+    #[serial]
+    fn wait_times_out_without_notification() {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        let start = Instant::now();
+        let mut timed_out = 0;
+        unsafe { ecall_condvar_wait_timeout(id, 50, &mut timed_out) }
+            .into_result()
+            .unwrap();
+
+        assert_ne!(timed_out, 0);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    #[serial]
+    fn notification_wakes_up_waiter() {
+        let enclave = EnclaveBuilder::from(ENCLAVE).create().unwrap();
+        let id = *enclave.id();
+
+        let start = Instant::now();
+        let mut timed_out = 0;
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                unsafe { ecall_condvar_wait_timeout(id, 60_000, &mut timed_out) }
+                    .into_result()
+                    .unwrap();
+            });
+            thread::sleep(Duration::from_millis(50));
+            unsafe { ecall_condvar_notify(id) }.into_result().unwrap();
+            waiter.join().unwrap();
+        });
+
+        assert_eq!(timed_out, 0);
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+}
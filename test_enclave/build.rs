@@ -25,37 +25,102 @@ struct EdgerFiles {
 
 const EDGER_FILE: &str = "src/enclave.edl";
 const ENCLAVE_FILE: &str = "src/enclave.c";
+const OCALL_DEFAULTS_FILE: &str = "src/ocall_defaults.c";
 const ENCLAVE_LINKER_SCRIPT: &str = "src/enclave.lds";
 const ENCLAVE_NAME: &str = "enclave";
 const ENCLAVE_NAME_KSS: &str = "enclave_kss";
 const ENCLAVE_NAME_PCL: &str = "enclave_pcl";
+const ENCLAVE_NAME_UNWIND: &str = "enclave_unwind";
+const ENCLAVE_NAME_DEBUG_ALLOC: &str = "enclave_debug_alloc";
+const ENCLAVE_NAME_THREAD_CACHE: &str = "enclave_thread_cache";
 const ENCLAVE_CONFIG: &str = "src/config.xml";
 const ENCLAVE_CONFIG_KSS: &str = "src/config_kss.xml";
 const ENCLAVE_PCL_KEY: &str = "src/pcl_key.bin";
+const TRUSTED_DIR: &str = "trusted";
+const TRUSTED_LIBRARY: &str = "libtest_enclave_trusted.a";
+/// The cargo profile, and feature, of the trusted library for the unwind
+/// enclave. The profile has `panic = "unwind"`.
+const TRUSTED_UNWIND: &str = "unwind";
+/// The cargo profile, and feature, of the trusted library for the debug
+/// allocator enclave. The feature uses a `DebugAllocator` as the global
+/// allocator.
+const TRUSTED_DEBUG_ALLOC: &str = "debug-alloc";
+/// The cargo profile, and feature, of the trusted library for the thread cache
+/// enclave. The feature uses a `ThreadCache` as the global allocator.
+const TRUSTED_THREAD_CACHE: &str = "thread-cache";
+/// Paths, relative to the repo root, that the trusted library is built from.
+const TRUSTED_LIBRARY_SOURCES: &[&str] = &[
+    "alloc/Cargo.toml",
+    "alloc/src",
+    "io/Cargo.toml",
+    "io/src",
+    "panic/Cargo.toml",
+    "panic/src",
+    "panic/sys/Cargo.toml",
+    "panic/sys/src",
+    "sync/Cargo.toml",
+    "sync/src",
+    "test_enclave/trusted/Cargo.toml",
+    "test_enclave/trusted/src",
+];
 
 fn main() {
     let root_dir = root_dir();
     let edger_files = build_enclave_definitions(root_dir.join(EDGER_FILE));
+    let trusted_library = build_trusted_library(root_dir.join(TRUSTED_DIR), None);
+    let trusted_library_unwind =
+        build_trusted_library(root_dir.join(TRUSTED_DIR), Some(TRUSTED_UNWIND));
+    let trusted_library_debug_alloc =
+        build_trusted_library(root_dir.join(TRUSTED_DIR), Some(TRUSTED_DEBUG_ALLOC));
+    let trusted_library_thread_cache =
+        build_trusted_library(root_dir.join(TRUSTED_DIR), Some(TRUSTED_THREAD_CACHE));
 
     build_enclave_binary(
         [root_dir.join(ENCLAVE_FILE), edger_files.trusted.clone()],
+        &trusted_library,
         ENCLAVE_CONFIG,
         ENCLAVE_NAME,
         None,
     );
     build_enclave_binary(
         [root_dir.join(ENCLAVE_FILE), edger_files.trusted.clone()],
+        &trusted_library,
         ENCLAVE_CONFIG_KSS,
         ENCLAVE_NAME_KSS,
         None,
     );
     build_enclave_binary(
         [root_dir.join(ENCLAVE_FILE), edger_files.trusted.clone()],
+        &trusted_library,
         ENCLAVE_CONFIG,
         ENCLAVE_NAME_PCL,
         Some(ENCLAVE_PCL_KEY),
     );
-    build_untrusted_library(&edger_files.untrusted);
+    build_enclave_binary(
+        [root_dir.join(ENCLAVE_FILE), edger_files.trusted.clone()],
+        &trusted_library_unwind,
+        ENCLAVE_CONFIG,
+        ENCLAVE_NAME_UNWIND,
+        None,
+    );
+    build_enclave_binary(
+        [root_dir.join(ENCLAVE_FILE), edger_files.trusted.clone()],
+        &trusted_library_debug_alloc,
+        ENCLAVE_CONFIG,
+        ENCLAVE_NAME_DEBUG_ALLOC,
+        None,
+    );
+    build_enclave_binary(
+        [root_dir.join(ENCLAVE_FILE), edger_files.trusted.clone()],
+        &trusted_library_thread_cache,
+        ENCLAVE_CONFIG,
+        ENCLAVE_NAME_THREAD_CACHE,
+        None,
+    );
+    build_untrusted_library([
+        edger_files.untrusted.clone(),
+        root_dir.join(OCALL_DEFAULTS_FILE),
+    ]);
 
     let mut untrusted_header = edger_files.untrusted;
     untrusted_header.set_extension("h");
@@ -96,6 +161,8 @@ fn build_enclave_definitions<P: AsRef<Path>>(edl_file: P) -> EdgerFiles {
     let out_dir = mc_sgx_core_build::build_output_dir();
     command
         .current_dir(&out_dir)
+        .arg("--search-path")
+        .arg(mc_sgx_core_build::sgx_include_dir())
         .arg(edl_file.as_ref().as_os_str());
     let status = command.status().expect("Failed to run edger8r");
     match status.code().unwrap() {
@@ -110,11 +177,72 @@ fn build_enclave_definitions<P: AsRef<Path>>(edl_file: P) -> EdgerFiles {
     EdgerFiles { trusted, untrusted }
 }
 
+/// Build the Rust static library that provides the Rust implemented ecalls.
+///
+/// The library is built with its own invocation of cargo, and its own target
+/// directory, since it needs to be built as a stand alone `no_std` static
+/// library that gets linked into the enclave binary.
+///
+/// # Arguments
+///
+/// * `crate_dir` - The directory of the trusted library crate.
+/// * `variant` - The cargo profile, and feature, to build the library with for
+///   an enclave that differs from the default one, e.g. [`TRUSTED_UNWIND`].
+///   `None` builds the library for the default enclave, with the `release`
+///   profile and no features.
+///
+/// # Returns
+/// The full path to the resultant static library.
+fn build_trusted_library<P: AsRef<Path>>(crate_dir: P, variant: Option<&str>) -> PathBuf {
+    let crate_dir = crate_dir.as_ref();
+    // The trusted library depends on the other crates in this repo, so
+    // rebuild whenever any of their sources change. Cargo will determine if
+    // anything actually needs to be rebuilt.
+    let repo_dir = crate_dir
+        .parent()
+        .and_then(Path::parent)
+        .expect("Trusted library should be in the repo's `test_enclave` dir");
+    for path in TRUSTED_LIBRARY_SOURCES {
+        rerun_if_changed!(repo_dir
+            .join(path)
+            .to_str()
+            .expect("Invalid UTF-8 in trusted library source path"));
+    }
+
+    let target_dir = mc_sgx_core_build::build_output_dir().join("trusted");
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let profile = variant.unwrap_or("release");
+    let mut command = Command::new(cargo);
+    command
+        .arg("build")
+        .arg("--profile")
+        .arg(profile)
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        // Flags meant for the consuming crate's build should not leak into
+        // the trusted library build.
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("CARGO_TARGET_DIR");
+    if let Some(feature) = variant {
+        command.arg("--features").arg(feature);
+    }
+    let status = command.status().expect("Failed to build trusted library");
+    match status.code().unwrap() {
+        0 => (),
+        code => panic!("Building trusted library exited with code {}", code),
+    }
+
+    target_dir.join(profile).join(TRUSTED_LIBRARY)
+}
+
 /// Create enclave binary.  The binary is a shared library.
 ///
 /// # Arguments
 ///
 /// * `files` - The source files to include in the binary
+/// * `trusted_library` - The Rust static library to link into the binary
 /// * `config` - The configuration file to use for signing the binary
 /// * `name` - The enclave name to use for generating output files
 /// * `keyfile` - The key file used for encrypting the enclave
@@ -122,7 +250,13 @@ fn build_enclave_definitions<P: AsRef<Path>>(edl_file: P) -> EdgerFiles {
 /// # Returns
 /// The full path to resultant binary file.  This binary will be signed and
 /// ready for use in `sgx_create_enclave()`.
-fn build_enclave_binary<P>(files: P, config: &str, name: &str, keyfile: Option<&str>) -> PathBuf
+fn build_enclave_binary<P>(
+    files: P,
+    trusted_library: &Path,
+    config: &str,
+    name: &str,
+    keyfile: Option<&str>,
+) -> PathBuf
 where
     P: IntoIterator,
     P: Clone,
@@ -156,7 +290,7 @@ where
 
     let static_name = format!("lib{}.a", name);
     let static_enclave = mc_sgx_core_build::build_output_dir().join(static_name);
-    let dynamic_enclave = build_dynamic_enclave_binary(static_enclave, keyfile);
+    let dynamic_enclave = build_dynamic_enclave_binary(static_enclave, trusted_library, keyfile);
     if let Some(key) = keyfile {
         encrypt_enclave_binary(dynamic_enclave.clone(), key);
     }
@@ -174,11 +308,13 @@ where
 /// # Arguments
 ///
 /// * `static_enclave` - The static enclave binary
+/// * `trusted_library` - The Rust static library to link into the binary
 /// * `keyfile` - The key file used to encrypt the enclave
 /// # Returns
 /// The full path to resultant shared library file.
 fn build_dynamic_enclave_binary<P: AsRef<Path>>(
     static_enclave: P,
+    trusted_library: &Path,
     keyfile: Option<&str>,
 ) -> PathBuf {
     let mut dynamic_enclave = PathBuf::from(static_enclave.as_ref());
@@ -222,6 +358,11 @@ fn build_dynamic_enclave_binary<P: AsRef<Path>>(
     command
         .arg("--no-whole-archive")
         .arg(static_enclave.as_ref().to_str().unwrap())
+        .arg(
+            trusted_library
+                .to_str()
+                .expect("Invalid UTF-8 in trusted library path"),
+        )
         .args(&["-lsgx_tstdc", "-lsgx_tcxx", "-lsgx_tcrypto", &tservice])
         .arg("--end-group")
         .arg("-Bstatic")
@@ -229,6 +370,9 @@ fn build_dynamic_enclave_binary<P: AsRef<Path>>(
         .arg("-pie")
         .arg("-eenclave_entry")
         .arg("--export-dynamic")
+        // The unwinder of the unwind enclave finds the unwind tables through
+        // the `.eh_frame_hdr` section
+        .arg("--eh-frame-hdr")
         .args(&["--defsym", "__ImageBase=0"])
         .arg("--gc-sections")
         .arg(&format!("--version-script={}", ENCLAVE_LINKER_SCRIPT));
@@ -332,22 +476,31 @@ fn get_signing_key() -> PathBuf {
 ///
 /// # Arguments
 ///
-/// * `untrusted_file` - The untrusted C file generated from `edger8r`
+/// * `files` - The untrusted C file generated from `edger8r` and the default
+///   ocall implementations
 ///
 /// # Returns
 /// The full path to resultant untrusted library.
-fn build_untrusted_library<P: AsRef<Path>>(untrusted_file: P) -> PathBuf {
+fn build_untrusted_library<P>(files: P) -> PathBuf
+where
+    P: IntoIterator,
+    P: Clone,
+    P::Item: AsRef<Path>,
+{
+    for file in files.clone() {
+        rerun_if_changed!(file
+            .as_ref()
+            .to_str()
+            .expect("Invalid UTF-8 in untrusted C file"));
+    }
+
+    // The untrusted library runs on the host, so it uses the host's C library
+    // rather than the enclave's `tlibc`
     let include_string = mc_sgx_core_build::sgx_include_string();
-    let tlibc_string = mc_sgx_core_build::sgx_include_dir()
-        .join("tlibc")
-        .to_str()
-        .expect("Invalid UTF-8 in tlibc include dir")
-        .to_owned();
 
     Build::new()
-        .file(untrusted_file)
+        .files(files)
         .include(include_string)
-        .include(tlibc_string)
         .compile("untrusted");
 
     let mut untrusted_object = mc_sgx_core_build::build_output_dir();
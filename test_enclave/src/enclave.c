@@ -1,6 +1,9 @@
 #include <stddef.h>
+#include <stdint.h>
 
 void ocall_stderr(const void * input, size_t len);
+void ocall_stdout(const void * input, size_t len);
+void ocall_log(uint32_t level, const char * target, size_t target_len, const char * module_path, size_t module_path_len, const char * msg, size_t msg_len);
 
 // Copyright (c) 2022 The MobileCoin Foundation
 /*
@@ -23,3 +26,33 @@ void ecall_round_trip_to_stderr(const void* input, size_t len) {
     ocall_stderr(input, len);
 }
 
+/*
+ * A thin wrapper that will take the provided message from the untrusted side
+ * and pipe it back out through an ocall to the untrusted side again...
+ * This shows the passing of messages via stdout through the enclave
+ *
+ * \param input: The input message. Since this will be used in rust, assume
+ *  utf8.
+ * \param len: The length of input, in bytes
+ */
+void ecall_round_trip_to_stdout(const void* input, size_t len) {
+    ocall_stdout(input, len);
+}
+
+/*
+ * A thin wrapper that will take the provided log message from the untrusted
+ * side and pipe it back out through `ocall_log()` to the untrusted side
+ * again.
+ *
+ * \param level: The level of the message, 1 (error) through 5 (trace).
+ * \param target: The target of the message. Assume utf8.
+ * \param target_len: The length of target, in bytes
+ * \param module_path: The module path of the message, empty when there is
+ *  none. Assume utf8.
+ * \param module_path_len: The length of module_path, in bytes
+ * \param msg: The message. Assume utf8.
+ * \param msg_len: The length of msg, in bytes
+ */
+void ecall_round_trip_to_log(uint32_t level, const char* target, size_t target_len, const char* module_path, size_t module_path_len, const char* msg, size_t msg_len) {
+    ocall_log(level, target, target_len, module_path, module_path_len, msg, msg_len);
+}
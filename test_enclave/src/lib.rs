@@ -8,6 +8,21 @@ pub static ENCLAVE_KSS: &[u8] =
 pub static ENCLAVE_PCL: &[u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/libenclave_pcl.signed.so"));
 pub static ENCLAVE_PCL_KEY: &[u8] = include_bytes!("pcl_key.bin");
+/// The test enclave built with `panic = "unwind"`, so that panics can be
+/// caught.
+pub static ENCLAVE_UNWIND: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/libenclave_unwind.signed.so"));
+/// The test enclave with a `DebugAllocator` as its global allocator, so that
+/// its outstanding allocations can be listed.
+pub static ENCLAVE_DEBUG_ALLOC: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/libenclave_debug_alloc.signed.so"
+));
+/// The test enclave with a `ThreadCache` as its global allocator.
+pub static ENCLAVE_THREAD_CACHE: &[u8] = include_bytes!(concat!(
+    env!("OUT_DIR"),
+    "/libenclave_thread_cache.signed.so"
+));
 
 use mc_sgx_core_sys_types::sgx_status_t;
 use mc_sgx_urts_sys_types::sgx_enclave_id_t;
@@ -0,0 +1,92 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+/*
+ * Default, mostly no-op, implementations of the ocalls in `enclave.edl`.
+ *
+ * The untrusted bridge references every ocall of the enclave. Consumers of
+ * the test enclave that only exercise some of the ecalls would otherwise need
+ * to provide all of the ocalls in order to link. These are weak symbols so any
+ * consumer providing its own implementation will take precedence.
+ *
+ * The clock is a real monotonic clock, and timed waits sleep, so that timed
+ * waits in the enclave still time out without spinning.
+ */
+/* For `clock_gettime()`, regardless of the C standard compiled against */
+#define _POSIX_C_SOURCE 199309L
+
+#include <stddef.h>
+#include <stdint.h>
+#include <time.h>
+
+__attribute__((weak)) void ocall_stderr(const void* input, size_t len) {
+    (void)input;
+    (void)len;
+}
+
+__attribute__((weak)) void ocall_stdout(const void* input, size_t len) {
+    (void)input;
+    (void)len;
+}
+
+__attribute__((weak)) void ocall_log(uint32_t level, const char* target, size_t target_len, const char* module_path, size_t module_path_len, const char* msg, size_t msg_len) {
+    (void)level;
+    (void)target;
+    (void)target_len;
+    (void)module_path;
+    (void)module_path_len;
+    (void)msg;
+    (void)msg_len;
+}
+
+__attribute__((weak)) void* ocall_untrusted_alloc(size_t size, size_t align) {
+    (void)size;
+    (void)align;
+    return NULL;
+}
+
+__attribute__((weak)) void ocall_untrusted_free(void* ptr, size_t size, size_t align) {
+    (void)ptr;
+    (void)size;
+    (void)align;
+}
+
+__attribute__((weak)) void ocall_reenter(size_t depth) {
+    (void)depth;
+}
+
+__attribute__((weak)) uint64_t ocall_untrusted_clock_now(void) {
+    struct timespec now;
+    if (clock_gettime(CLOCK_MONOTONIC, &now) != 0) {
+        return 0;
+    }
+    return (uint64_t)now.tv_sec * 1000000000 + (uint64_t)now.tv_nsec;
+}
+
+/*
+ * The longest the default `ocall_condvar_wait_timeout()` sleeps for, in
+ * nanoseconds. The default `ocall_condvar_wake()` can't wake up the sleeper, so
+ * the enclave has to re-check for notifications after each sleep.
+ */
+#define CONDVAR_POLL_NANOS 1000000
+
+__attribute__((weak)) int ocall_condvar_wait_timeout(size_t key, uint32_t sequence, uint64_t timeout_nanos) {
+    (void)key;
+    (void)sequence;
+    uint64_t nanos = timeout_nanos < CONDVAR_POLL_NANOS ? timeout_nanos : CONDVAR_POLL_NANOS;
+    struct timespec sleep = { .tv_sec = 0, .tv_nsec = (long)nanos };
+    nanosleep(&sleep, NULL);
+    return nanos == timeout_nanos;
+}
+
+__attribute__((weak)) void ocall_condvar_wake(size_t key, uint32_t sequence) {
+    (void)key;
+    (void)sequence;
+}
+
+__attribute__((weak)) void ocall_panic(const char* file, size_t file_len, uint32_t line, uint32_t column, const char* msg, size_t msg_len) {
+    (void)file;
+    (void)file_len;
+    (void)line;
+    (void)column;
+    (void)msg;
+    (void)msg_len;
+}
@@ -0,0 +1,310 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising the enclave's global allocator, which differs
+//! between the test enclaves, and the host memory of [`mc_sgx_alloc`].
+
+use alloc::alloc::{alloc, alloc_zeroed, dealloc, realloc, Layout};
+use alloc::vec::Vec;
+use core::ffi::c_int;
+use core::mem;
+use core::ptr;
+use core::slice;
+use mc_sgx_alloc::{UntrustedBox, UntrustedVec};
+
+/// The number of allocations each round of [`ecall_alloc_churn()`] keeps live
+const CHURN_LIVE: usize = 16;
+
+/// Allocate `start_size` bytes and grow them, by reallocating to double the
+/// size, until they are at least `end_size` bytes.
+///
+/// # Arguments
+/// * `align` - The alignment of the allocation. Must be a power of two.
+/// * `start_size` - The size of the initial allocation. Must be non zero.
+/// * `end_size` - The size to grow the allocation to.
+/// * `preserved` - Output, non zero if the contents and alignment of the
+///   allocation were preserved by every reallocation.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_realloc(
+    align: usize,
+    start_size: usize,
+    end_size: usize,
+    preserved: *mut c_int,
+) {
+    let mut layout = Layout::from_size_align(start_size, align).expect("Invalid layout");
+    let mut intact = true;
+    // SAFETY: `layout` has a non zero size and `ptr` is only accessed within
+    // the size of the current layout.
+    unsafe {
+        let mut ptr = alloc(layout);
+        assert!(!ptr.is_null(), "Failed to allocate {start_size} bytes");
+        fill(ptr, layout.size());
+        while layout.size() < end_size {
+            let new_size = (layout.size() * 2).min(end_size);
+            ptr = realloc(ptr, layout, new_size);
+            assert!(!ptr.is_null(), "Failed to reallocate to {new_size} bytes");
+            intact &= ptr as usize % align == 0 && is_filled(ptr, layout.size());
+            fill(ptr, new_size);
+            layout = Layout::from_size_align_unchecked(new_size, align);
+        }
+        dealloc(ptr, layout);
+    }
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *preserved = intact.into() };
+}
+
+/// Allocate `size` zeroed bytes and check that they are zero.
+///
+/// # Arguments
+/// * `align` - The alignment of the allocation. Must be a power of two.
+/// * `size` - The size of the allocation. Must be non zero.
+/// * `zeroed` - Output, non zero if the allocation was zeroed and aligned.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_zeroed(align: usize, size: usize, zeroed: *mut c_int) {
+    let layout = Layout::from_size_align(size, align).expect("Invalid layout");
+    // SAFETY: `layout` has a non zero size and `ptr` is only accessed within
+    // `size`.
+    let all_zero = unsafe {
+        let ptr = alloc_zeroed(layout);
+        assert!(!ptr.is_null(), "Failed to allocate {size} bytes");
+        let all_zero =
+            ptr as usize % align == 0 && slice::from_raw_parts(ptr, size).iter().all(|b| *b == 0);
+        // Dirty the memory so a later allocation reusing it isn't zero by
+        // chance.
+        fill(ptr, size);
+        dealloc(ptr, layout);
+        all_zero
+    };
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *zeroed = all_zero.into() };
+}
+
+/// Try to reserve `size` bytes within an allocation limit of `limit` bytes.
+///
+/// # Arguments
+/// * `limit` - The limit on the bytes allocated while reserving.
+/// * `size` - The number of bytes to reserve.
+/// * `allocated` - Output, non zero if the bytes were reserved.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_limit(limit: usize, size: usize, allocated: *mut c_int) {
+    let reserved = mc_sgx_alloc::with_alloc_limit(limit, || {
+        let mut buffer = Vec::<u8>::new();
+        buffer.try_reserve_exact(size).is_ok()
+    });
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *allocated = reserved.into() };
+}
+
+/// Repeatedly allocate and free small blocks with the global allocator, for
+/// benchmarking contention between threads.
+///
+/// Each round allocates [`CHURN_LIVE`] blocks and then frees them. The thread
+/// cache enclave, built with the `thread-cache` feature, has a
+/// [`ThreadCache`](mc_sgx_alloc::ThreadCache) as its global allocator.
+///
+/// # Arguments
+/// * `size` - The size of each block. Must be non zero.
+/// * `rounds` - The number of rounds to run.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_churn(size: usize, rounds: usize) {
+    // The TCS policy of the test enclave is unbind, so the blocks cached by
+    // the thread cache enclave must be flushed however the ecall returns.
+    // Nothing is cached in the other enclaves.
+    mc_sgx_alloc::with_thread_cache(|| {
+        let layout = Layout::from_size_align(size, 8).expect("Invalid layout");
+        for _ in 0..rounds {
+            churn(layout);
+        }
+    })
+}
+
+/// Free a block of `size` bytes, which the thread cache enclave caches, then
+/// try to reserve `len` bytes, returning early on failure.
+///
+/// Nothing flushes the cache explicitly, the early return leaves
+/// [`with_thread_cache()`](mc_sgx_alloc::with_thread_cache) with the block
+/// still cached.
+///
+/// # Arguments
+/// * `size` - The size of the block. Must be non zero.
+/// * `len` - The number of bytes to reserve.
+/// * `reserved` - Output, non zero if the bytes were reserved.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_cache_early_return(size: usize, len: usize, reserved: *mut c_int) {
+    let result = mc_sgx_alloc::with_thread_cache(|| {
+        churn(Layout::from_size_align(size, 8).ok()?);
+        let mut buffer = Vec::<u8>::new();
+        buffer.try_reserve_exact(len).ok()?;
+        Some(buffer)
+    });
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *reserved = result.is_some().into() };
+}
+
+/// Allocate [`CHURN_LIVE`] blocks of `layout` and then free them.
+fn churn(layout: Layout) {
+    let mut blocks = [ptr::null_mut(); CHURN_LIVE];
+    // SAFETY: `layout` has a non zero size and each block is freed with the
+    // layout it was allocated with.
+    unsafe {
+        for block in blocks.iter_mut() {
+            *block = alloc(layout);
+            assert!(
+                !block.is_null(),
+                "Failed to allocate {} bytes",
+                layout.size()
+            );
+        }
+        for block in blocks {
+            dealloc(block, layout);
+        }
+    }
+}
+
+/// Count the blocks of `size` bytes which can be allocated with the global
+/// allocator before it runs out of memory, freeing them afterwards.
+///
+/// # Arguments
+/// * `size` - The size of each block. Must be at least the size of a pointer.
+/// * `count` - Output, the number of blocks allocated.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_free_blocks(size: usize, count: *mut usize) {
+    let layout = Layout::from_size_align(size, mem::align_of::<*mut u8>()).expect("Invalid layout");
+    assert!(
+        size >= mem::size_of::<*mut u8>(),
+        "Blocks can't hold a pointer"
+    );
+    let mut allocated = 0;
+    // Each block holds the previously allocated block, so that no other
+    // memory is needed to free them.
+    let mut last = ptr::null_mut::<u8>();
+    // SAFETY: `layout` is large enough, and aligned, for a pointer and each
+    // block is freed with it.
+    unsafe {
+        loop {
+            let block = alloc(layout);
+            if block.is_null() {
+                break;
+            }
+            (block as *mut *mut u8).write(last);
+            last = block;
+            allocated += 1;
+        }
+        while !last.is_null() {
+            let previous = (last as *mut *mut u8).read();
+            dealloc(last, layout);
+            last = previous;
+        }
+    }
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *count = allocated };
+}
+
+/// Allocate `size` bytes with the global allocator and, while the allocation is
+/// live, write the outstanding allocations to the hosts stderr sink with
+/// [`DebugAllocator::dump_outstanding()`](mc_sgx_alloc::DebugAllocator::dump_outstanding).
+///
+/// Only the debug allocator enclave, built with the `debug-alloc` feature,
+/// lists its allocations. The other enclaves abort.
+///
+/// # Arguments
+/// * `align` - The alignment of the allocation. Must be a power of two.
+/// * `size` - The size of the allocation. Must be non zero.
+#[no_mangle]
+pub extern "C" fn ecall_alloc_dump_outstanding(align: usize, size: usize) {
+    let layout = Layout::from_size_align(size, align).expect("Invalid layout");
+    // SAFETY: `layout` has a non zero size and `ptr` is freed with it.
+    unsafe {
+        let ptr = alloc(layout);
+        assert!(!ptr.is_null(), "Failed to allocate {size} bytes");
+        dump_outstanding();
+        dealloc(ptr, layout);
+    }
+}
+
+/// Write the outstanding allocations of the global allocator to the hosts
+/// stderr sink
+#[cfg(feature = "debug-alloc")]
+fn dump_outstanding() {
+    crate::ALLOCATOR
+        .dump_outstanding()
+        .expect("Failed to write the outstanding allocations");
+}
+
+/// The global allocator doesn't track its allocations without the
+/// `debug-alloc` feature
+#[cfg(not(feature = "debug-alloc"))]
+fn dump_outstanding() {
+    panic!("Only the debug allocator enclave lists its outstanding allocations");
+}
+
+/// Copy `value` into host memory and back out again.
+///
+/// # Arguments
+/// * `value` - The value to copy.
+/// * `copied` - Output, the value copied back out of the host memory.
+#[no_mangle]
+pub extern "C" fn ecall_untrusted_box_round_trip(value: u64, copied: *mut u64) {
+    let boxed = UntrustedBox::new(value).expect("Failed to allocate host memory");
+    let value = boxed.copy_out();
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *copied = value };
+}
+
+/// Copy `len` bytes into host memory, in pieces so that the host memory
+/// grows, and back out again.
+///
+/// # Arguments
+/// * `len` - The number of bytes to copy.
+/// * `intact` - Output, non zero if the bytes copied back out of the host
+///   memory are those copied in.
+#[no_mangle]
+pub extern "C" fn ecall_untrusted_vec_round_trip(len: usize, intact: *mut c_int) {
+    let mut piece = [0u8; 1000];
+    let mut untrusted = UntrustedVec::new();
+    while untrusted.len() < len {
+        let piece_len = piece.len().min(len - untrusted.len());
+        let piece = &mut piece[..piece_len];
+        // SAFETY: `piece` is valid for writes of its length
+        unsafe { fill(piece.as_mut_ptr(), piece_len) };
+        untrusted
+            .copy_in(piece)
+            .expect("Failed to allocate host memory");
+    }
+
+    let copied = untrusted.copy_out();
+    let matches = copied.len() == len
+        && copied
+            .chunks(piece.len())
+            // SAFETY: Each chunk is valid for reads of its length
+            .all(|chunk| unsafe { is_filled(chunk.as_ptr(), chunk.len()) });
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *intact = matches.into() };
+}
+
+/// Fill `len` bytes at `ptr` with a pattern based on the offset
+///
+/// # Safety
+/// `ptr` must be valid for writes of `len` bytes.
+unsafe fn fill(ptr: *mut u8, len: usize) {
+    for (offset, byte) in slice::from_raw_parts_mut(ptr, len).iter_mut().enumerate() {
+        *byte = offset as u8 | 1;
+    }
+}
+
+/// Are the `len` bytes at `ptr` the pattern written by [`fill()`]
+///
+/// # Safety
+/// `ptr` must be valid for reads of `len` bytes.
+unsafe fn is_filled(ptr: *const u8, len: usize) -> bool {
+    slice::from_raw_parts(ptr, len)
+        .iter()
+        .enumerate()
+        .all(|(offset, byte)| *byte == offset as u8 | 1)
+}
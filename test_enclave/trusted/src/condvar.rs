@@ -0,0 +1,45 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising the timed waits of [`mc_sgx_sync::Condvar`].
+
+use core::ffi::c_int;
+use core::time::Duration;
+use mc_sgx_sync::{Condvar, Mutex};
+
+/// Whether [`ecall_condvar_notify()`] has been called.
+static NOTIFIED: Mutex<bool> = Mutex::new(false);
+
+/// The condition variable under test.
+static CONDVAR: Condvar = Condvar::new();
+
+/// Wait on [`CONDVAR`], for at most `timeout_millis`, until
+/// [`ecall_condvar_notify()`] has been called.
+///
+/// The notification is consumed so that subsequent calls will wait again.
+///
+/// # Arguments
+/// * `timeout_millis` - The maximum time to wait, in milliseconds.
+/// * `timed_out` - Output, non zero if the timeout expired prior to being
+///   notified.
+#[no_mangle]
+pub extern "C" fn ecall_condvar_wait_timeout(timeout_millis: u64, timed_out: *mut c_int) {
+    let guard = NOTIFIED.lock().expect("Mutex has been poisoned");
+    let (mut guard, result) = CONDVAR
+        .wait_timeout_while(guard, Duration::from_millis(timeout_millis), |notified| {
+            !*notified
+        })
+        .expect("Mutex has been poisoned");
+    *guard = false;
+
+    // SAFETY: The edger8r generated bridge provides a valid pointer for the
+    // `[out]` parameter.
+    unsafe { *timed_out = result.timed_out().into() };
+}
+
+/// Notify all of the waiters in [`ecall_condvar_wait_timeout()`].
+#[no_mangle]
+pub extern "C" fn ecall_condvar_notify() {
+    let mut guard = NOTIFIED.lock().expect("Mutex has been poisoned");
+    *guard = true;
+    CONDVAR.notify_all();
+}
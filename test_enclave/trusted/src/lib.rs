@@ -0,0 +1,29 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Rust implementations of the test enclave ecalls.
+//!
+//! This is built as a static library and linked into the test enclave
+//! alongside `enclave.c`. It allows for exercising the `mc-sgx-*` crates from
+//! inside of an actual enclave.
+
+#![no_std]
+
+extern crate alloc;
+// Provides the `#[panic_handler]` for the enclave
+extern crate mc_sgx_panic;
+
+#[cfg(not(any(feature = "debug-alloc", feature = "thread-cache")))]
+mc_sgx_alloc::allocator!(ALLOCATOR);
+#[cfg(feature = "debug-alloc")]
+mc_sgx_alloc::allocator!(ALLOCATOR, debug);
+#[cfg(feature = "thread-cache")]
+mc_sgx_alloc::allocator!(ALLOCATOR, mc_sgx_alloc::ThreadCache);
+
+mod allocator;
+mod condvar;
+mod nonpoison;
+mod once;
+mod panic;
+mod remutex;
+mod rwlock;
+mod unwind;
@@ -0,0 +1,110 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising the [`mc_sgx_sync::nonpoison`] primitives from
+//! multiple TCSs.
+
+use core::ffi::c_int;
+use core::hint;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use mc_sgx_sync::nonpoison::{Condvar, Mutex};
+
+/// The mutex under test. The value is the number of completed increments.
+static MUTEX: Mutex<usize> = Mutex::new(0);
+
+/// Set by [`ecall_nonpoison_mutex_release()`] to let
+/// [`ecall_nonpoison_mutex_hold()`] unlock [`MUTEX`].
+static RELEASE: AtomicBool = AtomicBool::new(false);
+
+/// Whether [`ecall_nonpoison_condvar_notify()`] has been called.
+static NOTIFIED: Mutex<bool> = Mutex::new(false);
+
+/// The condition variable under test.
+static CONDVAR: Condvar = Condvar::new();
+
+/// Lock [`MUTEX`] and increment its value, spinning for `spins` iterations
+/// between reading and writing the value.
+///
+/// Increments are lost if another TCS holds the lock at the same time.
+///
+/// # Arguments
+/// * `spins` - The number of iterations to spin for while holding the lock.
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_mutex_increment(spins: usize) {
+    let mut guard = MUTEX.lock();
+    let value = *guard;
+    for _ in 0..spins {
+        hint::spin_loop();
+    }
+    *guard = value + 1;
+}
+
+/// Get the number of completed increments of [`MUTEX`].
+///
+/// # Arguments
+/// * `value` - Output, the number of completed increments.
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_mutex_value(value: *mut usize) {
+    let guard = MUTEX.lock();
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *value = *guard };
+}
+
+/// Lock [`MUTEX`] and hold it until [`ecall_nonpoison_mutex_release()`] has
+/// been called.
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_mutex_hold() {
+    let _guard = MUTEX.lock();
+    while !RELEASE.swap(false, Ordering::SeqCst) {
+        hint::spin_loop();
+    }
+}
+
+/// Let [`ecall_nonpoison_mutex_hold()`] unlock [`MUTEX`].
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_mutex_release() {
+    RELEASE.store(true, Ordering::SeqCst);
+}
+
+/// Try to lock [`MUTEX`], without blocking.
+///
+/// # Arguments
+/// * `locked` - Output, non zero if the lock was acquired.
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_mutex_try_lock(locked: *mut c_int) {
+    let acquired = MUTEX.try_lock().is_ok();
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *locked = acquired.into() };
+}
+
+/// Wait on [`CONDVAR`], for at most `timeout_millis`, until
+/// [`ecall_nonpoison_condvar_notify()`] has been called.
+///
+/// The notification is consumed so that subsequent calls will wait again.
+///
+/// # Arguments
+/// * `timeout_millis` - The maximum time to wait, in milliseconds.
+/// * `timed_out` - Output, non zero if the timeout expired prior to being
+///   notified.
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_condvar_wait_timeout(timeout_millis: u64, timed_out: *mut c_int) {
+    let guard = NOTIFIED.lock();
+    let (mut guard, result) =
+        CONDVAR.wait_timeout_while(guard, Duration::from_millis(timeout_millis), |notified| {
+            !*notified
+        });
+    *guard = false;
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *timed_out = result.timed_out().into() };
+}
+
+/// Notify all of the waiters in [`ecall_nonpoison_condvar_wait_timeout()`].
+#[no_mangle]
+pub extern "C" fn ecall_nonpoison_condvar_notify() {
+    let mut guard = NOTIFIED.lock();
+    *guard = true;
+    CONDVAR.notify_all();
+}
@@ -0,0 +1,121 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising [`mc_sgx_sync::Once`] and [`mc_sgx_sync::LazyLock`]
+//! from multiple TCSs.
+
+use crate::unwind::catch_unwind;
+use core::ffi::c_int;
+use core::hint;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use mc_sgx_sync::{LazyLock, Once};
+
+/// The [`Once`] of [`ecall_once_call_once()`].
+static ONCE: Once = Once::new();
+
+/// The number of times the closure given to [`ONCE`] was called.
+static ONCE_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// The [`Once`] of [`ecall_once_poison()`] and
+/// [`ecall_once_call_once_force()`].
+static POISON_ONCE: Once = Once::new();
+
+/// The number of iterations the initialization of [`LAZY`] spins for.
+static LAZY_SPINS: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of times [`LAZY`] was initialized.
+static LAZY_INITS: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of initializations at the time [`LAZY`] was initialized.
+static LAZY: LazyLock<usize> = LazyLock::new(|| {
+    spin(LAZY_SPINS.load(Ordering::SeqCst));
+    LAZY_INITS.fetch_add(1, Ordering::SeqCst) + 1
+});
+
+/// Call [`Once::call_once()`] on [`ONCE`], whose closure spins for `spins`
+/// iterations and then counts that it was called.
+///
+/// # Arguments
+/// * `spins` - The number of iterations the closure spins for.
+/// * `calls` - Output, the number of times the closure was called, after
+///   [`Once::call_once()`] returned.
+#[no_mangle]
+pub extern "C" fn ecall_once_call_once(spins: usize, calls: *mut usize) {
+    ONCE.call_once(|| {
+        spin(spins);
+        ONCE_CALLS.fetch_add(1, Ordering::SeqCst);
+    });
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *calls = ONCE_CALLS.load(Ordering::SeqCst) };
+}
+
+/// Call [`Once::call_once()`] on [`POISON_ONCE`], panicking in the closure
+/// when `should_panic` is non zero.
+///
+/// # Arguments
+/// * `should_panic` - Non zero to panic in the closure.
+/// * `panicked` - Output, non zero if [`Once::call_once()`] panicked, from the
+///   closure or from the [`Once`] being poisoned.
+/// * `completed` - Output, non zero if the [`Once`] is completed afterwards.
+#[no_mangle]
+pub extern "C" fn ecall_once_poison(
+    should_panic: c_int,
+    panicked: *mut c_int,
+    completed: *mut c_int,
+) {
+    let result = catch_unwind(|| {
+        POISON_ONCE.call_once(|| assert_eq!(should_panic, 0, "Asked to panic"));
+    });
+
+    // SAFETY: The edger8r generated bridge provides valid `[out]` pointers.
+    unsafe {
+        *panicked = result.is_err().into();
+        *completed = POISON_ONCE.is_completed().into();
+    }
+}
+
+/// Call [`Once::call_once_force()`] on [`POISON_ONCE`].
+///
+/// # Arguments
+/// * `ran` - Output, non zero if the closure was called.
+/// * `poisoned` - Output, non zero if the closure was told the [`Once`] was
+///   poisoned.
+#[no_mangle]
+pub extern "C" fn ecall_once_call_once_force(ran: *mut c_int, poisoned: *mut c_int) {
+    let mut state = None;
+    POISON_ONCE.call_once_force(|once_state| state = Some(once_state.is_poisoned()));
+
+    // SAFETY: The edger8r generated bridge provides valid `[out]` pointers.
+    unsafe {
+        *ran = state.is_some().into();
+        *poisoned = state.unwrap_or_default().into();
+    }
+}
+
+/// Force [`LAZY`], whose initialization spins for `spins` iterations and then
+/// counts that it was called.
+///
+/// # Arguments
+/// * `spins` - The number of iterations the initialization spins for, only
+///   used by the first call.
+/// * `value` - Output, the value of [`LAZY`].
+/// * `inits` - Output, the number of initializations, after forcing.
+#[no_mangle]
+pub extern "C" fn ecall_lazy_lock_force(spins: usize, value: *mut usize, inits: *mut usize) {
+    // Only the first call's spins matter, later calls find it already set
+    let _ = LAZY_SPINS.compare_exchange(0, spins, Ordering::SeqCst, Ordering::SeqCst);
+    let forced = *LAZY;
+
+    // SAFETY: The edger8r generated bridge provides valid `[out]` pointers.
+    unsafe {
+        *value = forced;
+        *inits = LAZY_INITS.load(Ordering::SeqCst);
+    }
+}
+
+/// Spin for `spins` iterations, to widen the window for other TCSs to race.
+fn spin(spins: usize) {
+    for _ in 0..spins {
+        hint::spin_loop();
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising the panic handling of [`mc_sgx_panic`].
+
+use core::ffi::c_char;
+use core::slice;
+
+/// Panic with the provided message.
+///
+/// # Arguments
+/// * `msg` - The message to panic with. Should be UTF-8.
+/// * `msg_len` - The byte length of `msg`
+#[no_mangle]
+pub extern "C" fn ecall_panic(msg: *const c_char, msg_len: usize) {
+    // SAFETY: The edger8r generated bridge copies `msg_len` bytes of the
+    // `[in]` parameter into the enclave.
+    let bytes = unsafe { slice::from_raw_parts(msg as *const u8, msg_len) };
+    let message = core::str::from_utf8(bytes).unwrap_or("<invalid UTF-8>");
+    panic!("{message}");
+}
@@ -0,0 +1,38 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising [`mc_sgx_sync::ReentrantMutex`] with nested ecalls.
+
+use core::cell::Cell;
+use mc_sgx_core_sys_types::sgx_status_t;
+use mc_sgx_sync::ReentrantMutex;
+
+/// The lock under test. The value is the number of times it has been locked.
+static LOCK: ReentrantMutex<Cell<usize>> = ReentrantMutex::new(Cell::new(0));
+
+extern "C" {
+    /// Calls back into [`ecall_reentrant_lock()`] with `depth`.
+    fn ocall_reenter(depth: usize) -> sgx_status_t;
+}
+
+/// Lock the [`LOCK`] and then re-enter this ecall until the lock has been
+/// locked `depth` times on the current thread.
+///
+/// # Arguments
+/// * `depth` - The number of times to lock the [`LOCK`], including this call.
+/// * `count` - Output for the number of times [`LOCK`] has been locked, after
+///   all nested ecalls have returned.
+#[no_mangle]
+pub extern "C" fn ecall_reentrant_lock(depth: usize, count: *mut usize) {
+    let guard = LOCK.lock();
+    guard.set(guard.get() + 1);
+
+    if depth > 1 {
+        // SAFETY: The ocall is provided by the edger8r generated bridge.
+        let status = unsafe { ocall_reenter(depth - 1) };
+        assert_eq!(status, sgx_status_t::SGX_SUCCESS, "Failed to re-enter");
+    }
+
+    // SAFETY: The edger8r generated bridge provides a valid pointer for the
+    // `[out]` parameter.
+    unsafe { *count = guard.get() };
+}
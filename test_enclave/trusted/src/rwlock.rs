@@ -0,0 +1,92 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising [`mc_sgx_sync::RwLock`] from multiple TCSs.
+//!
+//! The readers and writers keep track of how many of them are currently inside
+//! of the lock. Any time one of them observes a state which should not be
+//! possible, e.g. a reader while a writer holds the lock, it is counted as a
+//! violation and reported back to the host.
+
+use core::hint;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use mc_sgx_sync::RwLock;
+
+/// The lock under test. The value is the number of completed writes.
+static LOCK: RwLock<usize> = RwLock::new(0);
+
+/// Number of readers currently holding the lock.
+static READERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of writers currently holding the lock.
+static WRITERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Acquire a read lock and hold it for `spins` iterations.
+///
+/// # Arguments
+/// * `spins` - The number of iterations to hold the lock for.
+/// * `violations` - Output for the number of times a writer was seen while
+///   holding the read lock.
+#[no_mangle]
+pub extern "C" fn ecall_rwlock_read(spins: usize, violations: *mut usize) {
+    let mut seen = 0;
+    {
+        let _guard = LOCK.read().expect("RwLock has been poisoned");
+        READERS.fetch_add(1, Ordering::SeqCst);
+        for _ in 0..spins {
+            if WRITERS.load(Ordering::SeqCst) != 0 {
+                seen += 1;
+            }
+            hint::spin_loop();
+        }
+        READERS.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // SAFETY: The edger8r generated bridge provides a valid pointer for the
+    // `[out]` parameter.
+    unsafe { *violations = seen };
+}
+
+/// Acquire the write lock, hold it for `spins` iterations and then increment
+/// the value protected by the lock.
+///
+/// The increment is split into a read and a later write of the value so that
+/// concurrent writers would lose updates.
+///
+/// # Arguments
+/// * `spins` - The number of iterations to hold the lock for.
+/// * `violations` - Output for the number of times another reader or writer
+///   was seen while holding the write lock.
+#[no_mangle]
+pub extern "C" fn ecall_rwlock_write(spins: usize, violations: *mut usize) {
+    let mut seen = 0;
+    {
+        let mut guard = LOCK.write().expect("RwLock has been poisoned");
+        WRITERS.fetch_add(1, Ordering::SeqCst);
+        let value = *guard;
+        for _ in 0..spins {
+            if READERS.load(Ordering::SeqCst) != 0 || WRITERS.load(Ordering::SeqCst) != 1 {
+                seen += 1;
+            }
+            hint::spin_loop();
+        }
+        *guard = value + 1;
+        WRITERS.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // SAFETY: The edger8r generated bridge provides a valid pointer for the
+    // `[out]` parameter.
+    unsafe { *violations = seen };
+}
+
+/// Get the number of completed writes.
+///
+/// # Arguments
+/// * `value` - Output for the number of completed writes.
+#[no_mangle]
+pub extern "C" fn ecall_rwlock_value(value: *mut usize) {
+    let guard = LOCK.read().expect("RwLock has been poisoned");
+
+    // SAFETY: The edger8r generated bridge provides a valid pointer for the
+    // `[out]` parameter.
+    unsafe { *value = *guard };
+}
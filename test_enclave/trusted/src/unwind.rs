@@ -0,0 +1,99 @@
+// Copyright (c) 2023 The MobileCoin Foundation
+
+//! Ecalls for exercising the unwinding of [`mc_sgx_panic`].
+//!
+//! Only the unwind enclave is built with the `unwind` feature and
+//! `panic = "unwind"`. The other enclaves still provide these ecalls, in order
+//! to link, but their panics abort the enclave.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::ffi::c_int;
+use mc_sgx_core_sys_types::sgx_status_t;
+#[cfg(feature = "unwind")]
+pub(crate) use mc_sgx_panic::{catch_ecall, catch_unwind, resume_unwind};
+#[cfg(not(feature = "unwind"))]
+pub(crate) use unsupported::{catch_ecall, catch_unwind, resume_unwind};
+
+/// Return `SGX_SUCCESS`, or panic, inside of [`mc_sgx_panic::catch_ecall()`].
+///
+/// # Arguments
+/// * `should_panic` - Non zero to panic.
+///
+/// # Returns
+/// `SGX_SUCCESS`, or `SGX_ERROR_UNEXPECTED` when the panic was caught.
+#[no_mangle]
+pub extern "C" fn ecall_catch_ecall(should_panic: c_int) -> sgx_status_t {
+    catch_ecall(|| {
+        assert_eq!(should_panic, 0, "Asked to panic");
+        sgx_status_t::SGX_SUCCESS
+    })
+}
+
+/// Panic with `value` as the message, inside of
+/// [`mc_sgx_panic::catch_unwind()`].
+///
+/// # Arguments
+/// * `value` - The value to panic with.
+/// * `caught` - Output, the value in the message of the caught panic.
+#[no_mangle]
+pub extern "C" fn ecall_catch_unwind(value: u64, caught: *mut u64) {
+    let payload = catch_unwind(|| panic!("{value}")).expect_err("Panic should have been caught");
+    let message = payload
+        .downcast::<String>()
+        .expect("Payload should be the panic message");
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *caught = message.parse().expect("Message should be the value") };
+}
+
+/// Unwind with `value` as the payload through a nested
+/// [`mc_sgx_panic::catch_unwind()`], which resumes unwinding to an outer
+/// [`mc_sgx_panic::catch_unwind()`].
+///
+/// # Arguments
+/// * `value` - The payload to unwind with.
+/// * `caught` - Output, the payload caught by the outer `catch_unwind()`.
+#[no_mangle]
+pub extern "C" fn ecall_resume_unwind(value: u64, caught: *mut u64) {
+    let payload = catch_unwind(|| {
+        let payload = catch_unwind(|| resume_unwind(Box::new(value)))
+            .expect_err("Inner unwind should have been caught");
+        resume_unwind(payload)
+    })
+    .expect_err("Resumed unwind should have been caught");
+    let value = payload
+        .downcast::<u64>()
+        .expect("Payload should be the value");
+
+    // SAFETY: The edger8r generated bridge provides a valid `[out]` pointer.
+    unsafe { *caught = *value };
+}
+
+/// Stand ins for enclaves without the `unwind` feature, where panics abort so
+/// there's never anything to catch.
+#[cfg(not(feature = "unwind"))]
+mod unsupported {
+    use alloc::boxed::Box;
+    use core::any::Any;
+    use core::panic::UnwindSafe;
+    use mc_sgx_core_sys_types::sgx_status_t;
+
+    pub(crate) fn catch_ecall<F>(f: F) -> sgx_status_t
+    where
+        F: FnOnce() -> sgx_status_t + UnwindSafe,
+    {
+        f()
+    }
+
+    pub(crate) fn catch_unwind<F, R>(f: F) -> Result<R, Box<dyn Any + Send + 'static>>
+    where
+        F: FnOnce() -> R + UnwindSafe,
+    {
+        Ok(f())
+    }
+
+    pub(crate) fn resume_unwind(_payload: Box<dyn Any + Send>) -> ! {
+        panic!("Unwinding needs the `unwind` feature");
+    }
+}